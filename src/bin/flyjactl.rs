@@ -0,0 +1,249 @@
+#[allow(dead_code)]
+#[path = "../ipc/protocol.rs"]
+mod protocol;
+
+use std::{
+    io::{BufRead, BufReader, Write},
+    os::unix::net::UnixStream,
+};
+
+use protocol::{Event, EventKind, Layout, Reply, Request, Response, Split, SOCKET_ENV};
+
+static USAGE: &str = "USAGE: flyjactl [--json] <command>
+
+Commands:
+\twindows                     : List the windows
+\tworkspaces                  : List the workspaces
+\toutputs                     : List the outputs
+\trules                       : List the window rules and the windows they match
+\tfocus <id>                  : Focus the window
+\tclose <id>                  : Ask the window to close
+\tmove <id> <workspace>       : Move the window to the workspace
+\tworkspace <workspace>       : Switch to the workspace
+\tlayout <layout>             : Change the layout of the current workspace:
+\t                              tile, master, scroll or stack
+\tsplit h|v                   : Change how the next window is split
+\tgaps inner|outer <pixels>   : Set the gaps between and around the tiles
+\tsubscribe window,workspace  : Print the events of these kinds
+\texec <cmd>                  : Run a command in the session";
+
+fn main() {
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    let json = match args.iter().position(|arg| arg == "--json") {
+        Some(index) => {
+            args.remove(index);
+            true
+        }
+        None => false,
+    };
+    let request = match parse_request(&args) {
+        Ok(request) => request,
+        Err(err) => {
+            eprintln!("{err}");
+            eprintln!();
+            eprintln!("{USAGE}");
+            std::process::exit(2);
+        }
+    };
+    if let Err(err) = run(request, json) {
+        eprintln!("flyjactl: {err}");
+        std::process::exit(1);
+    }
+}
+
+fn parse_request(args: &[String]) -> Result<Request, String> {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let number = |value: &str| -> Result<u64, String> {
+        value
+            .parse()
+            .map_err(|_| format!("`{value}` is not a number"))
+    };
+    let request = match args.as_slice() {
+        ["windows"] => Request::Windows,
+        ["workspaces"] => Request::Workspaces,
+        ["outputs"] => Request::Outputs,
+        ["rules"] => Request::Rules,
+        ["focus", id] => Request::Focus { id: number(id)? },
+        ["close", id] => Request::Close { id: number(id)? },
+        ["move", id, workspace] => Request::MoveToWorkspace {
+            id: number(id)?,
+            workspace: number(workspace)? as usize,
+        },
+        ["workspace", workspace] => Request::SwitchWorkspace {
+            workspace: number(workspace)? as usize,
+        },
+        ["layout", "tile"] => Request::SetLayout {
+            layout: Layout::Tile,
+        },
+        ["layout", "master"] => Request::SetLayout {
+            layout: Layout::Master,
+        },
+        ["layout", "scroll"] => Request::SetLayout {
+            layout: Layout::Scroll,
+        },
+        ["layout", "stack"] => Request::SetLayout {
+            layout: Layout::Stack,
+        },
+        ["split", "h"] => Request::SetSplit { split: Split::H },
+        ["split", "v"] => Request::SetSplit { split: Split::V },
+        ["gaps", "inner", gap] => Request::SetGaps {
+            inner: Some(number(gap)? as i32),
+            outer: None,
+        },
+        ["gaps", "outer", gap] => Request::SetGaps {
+            inner: None,
+            outer: Some(number(gap)? as i32),
+        },
+        ["subscribe", kinds] => {
+            let events = kinds
+                .split(',')
+                .map(|kind| match kind.trim() {
+                    "window" => Ok(EventKind::Window),
+                    "workspace" => Ok(EventKind::Workspace),
+                    other => Err(format!("Unknown event kind `{other}`")),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            Request::Subscribe { events }
+        }
+        ["exec", command @ ..] if !command.is_empty() => Request::Exec {
+            command: command.join(" "),
+        },
+        [] => return Err("No command given".to_string()),
+        _ => return Err(format!("Invalid command `{}`", args.join(" "))),
+    };
+    Ok(request)
+}
+
+fn run(request: Request, json: bool) -> Result<(), String> {
+    let path = std::env::var_os(SOCKET_ENV)
+        .ok_or_else(|| format!("{SOCKET_ENV} is not set, is flyja running?"))?;
+    let mut stream = UnixStream::connect(&path)
+        .map_err(|err| format!("Cannot connect to {}: {err}", path.to_string_lossy()))?;
+
+    let mut line = serde_json::to_string(&request).map_err(|err| err.to_string())?;
+    line.push('\n');
+    stream
+        .write_all(line.as_bytes())
+        .map_err(|err| format!("Cannot send the request: {err}"))?;
+
+    let mut lines = BufReader::new(stream).lines();
+    let line = lines
+        .next()
+        .ok_or("The connection was closed")?
+        .map_err(|err| format!("Cannot read the response: {err}"))?;
+    if json {
+        println!("{line}");
+    }
+    let response: Response =
+        serde_json::from_str(&line).map_err(|err| format!("Invalid response: {err}"))?;
+    let reply = match response {
+        Response::Ok(reply) => reply,
+        Response::Error(err) => return Err(err),
+    };
+    if !json {
+        print_reply(&reply);
+    }
+    if let Reply::Subscribed = reply {
+        for line in lines {
+            let line = line.map_err(|err| format!("Cannot read the event: {err}"))?;
+            if json {
+                println!("{line}");
+                continue;
+            }
+            match serde_json::from_str::<Event>(&line) {
+                Ok(event) => print_event(&event),
+                Err(err) => eprintln!("Invalid event: {err}"),
+            }
+        }
+    }
+    Ok(())
+}
+
+fn print_reply(reply: &Reply) {
+    match reply {
+        Reply::Done | Reply::Subscribed => {}
+        Reply::Windows { windows } => {
+            for window in windows {
+                println!(
+                    "{}{} workspace {} at {},{} {}x{} {} \"{}\"",
+                    if window.focused { "*" } else { " " },
+                    window.id,
+                    window.workspace,
+                    window.x,
+                    window.y,
+                    window.width,
+                    window.height,
+                    window.app_id.as_deref().unwrap_or("-"),
+                    window.title.as_deref().unwrap_or(""),
+                );
+            }
+        }
+        Reply::Workspaces { workspaces } => {
+            for workspace in workspaces {
+                println!(
+                    "{}{} {:?} {} windows",
+                    if workspace.focused { "*" } else { " " },
+                    workspace.index,
+                    workspace.layout,
+                    workspace.windows,
+                );
+            }
+        }
+        Reply::Rules { rules } => {
+            for rule in rules {
+                let mut actions = Vec::new();
+                if let Some(floating) = rule.floating {
+                    actions.push(if floating { "floating" } else { "tiled" }.to_string());
+                }
+                if let Some(workspace) = rule.workspace {
+                    actions.push(format!("workspace {workspace}"));
+                }
+                if let Some((width, height)) = rule.size {
+                    actions.push(format!("size {width}x{height}"));
+                }
+                if let Some(border) = rule.border {
+                    actions.push(if border { "border" } else { "no border" }.to_string());
+                }
+                let windows: Vec<String> = rule.windows.iter().map(u64::to_string).collect();
+                println!(
+                    "{} app_id \"{}\" title \"{}\": {} windows [{}]",
+                    rule.index,
+                    rule.app_id.as_deref().unwrap_or(""),
+                    rule.title.as_deref().unwrap_or(""),
+                    actions.join(", "),
+                    windows.join(", "),
+                );
+            }
+        }
+        Reply::Outputs { outputs } => {
+            for output in outputs {
+                println!(
+                    "{} ({} {}) at {},{} {}x{}@{:.3}Hz scale {}",
+                    output.name,
+                    output.make,
+                    output.model,
+                    output.x,
+                    output.y,
+                    output.width,
+                    output.height,
+                    output.refresh as f64 / 1000.0,
+                    output.scale,
+                );
+            }
+        }
+    }
+}
+
+fn print_event(event: &Event) {
+    match event {
+        Event::WindowOpened { window } => println!(
+            "window opened {} {} \"{}\"",
+            window.id,
+            window.app_id.as_deref().unwrap_or("-"),
+            window.title.as_deref().unwrap_or(""),
+        ),
+        Event::WindowClosed { id } => println!("window closed {id}"),
+        Event::WindowFocused { id } => println!("window focused {id}"),
+        Event::WorkspaceChanged { workspace } => println!("workspace changed {workspace}"),
+    }
+}
@@ -0,0 +1,521 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use inotify::{Inotify, WatchMask};
+use serde::Deserialize;
+use smithay::{
+    input::keyboard::{keysyms as xkb, Keysym, ModifiersState, XkbConfig},
+    reexports::calloop::{generic::Generic, Interest, LoopHandle, Mode, PostAction},
+};
+use xkbcommon::xkb::{keysym_from_name, KEYSYM_CASE_INSENSITIVE, KEYSYM_NO_FLAGS};
+
+use crate::{
+    focus::Direction,
+    input::KeyAction,
+    layout::ContainerMode,
+    rules::WindowRule,
+    shell::WindowElement,
+    state::{Backend, SplitState, WmStatus},
+    CalloopData, FlyJa,
+};
+
+/// A key with the modifiers which need to be held, written like `Logo+Shift+Return`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(try_from = "String")]
+pub struct KeyCombo {
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+    pub logo: bool,
+    pub keysym: Keysym,
+}
+
+impl KeyCombo {
+    fn from_modifiers(modifiers: &ModifiersState, keysym: Keysym) -> Self {
+        Self {
+            ctrl: modifiers.ctrl,
+            alt: modifiers.alt,
+            shift: modifiers.shift,
+            logo: modifiers.logo,
+            keysym,
+        }
+    }
+}
+
+impl TryFrom<String> for KeyCombo {
+    type Error = String;
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let mut combo = KeyCombo {
+            ctrl: false,
+            alt: false,
+            shift: false,
+            logo: false,
+            keysym: xkb::KEY_NoSymbol,
+        };
+        let mut parts: Vec<&str> = value.split('+').map(str::trim).collect();
+        let Some(key) = parts.pop().filter(|key| !key.is_empty()) else {
+            return Err(format!("binding `{value}` has no key"));
+        };
+        for modifier in parts {
+            match modifier.to_lowercase().as_str() {
+                "ctrl" | "control" => combo.ctrl = true,
+                "alt" | "mod1" => combo.alt = true,
+                "shift" => combo.shift = true,
+                "logo" | "super" | "mod4" => combo.logo = true,
+                _ => return Err(format!("unknown modifier `{modifier}` in `{value}`")),
+            }
+        }
+        combo.keysym = match keysym_from_name(key, KEYSYM_NO_FLAGS) {
+            xkb::KEY_NoSymbol => keysym_from_name(key, KEYSYM_CASE_INSENSITIVE),
+            keysym => keysym,
+        };
+        if combo.keysym == xkb::KEY_NoSymbol {
+            return Err(format!("unknown key `{key}` in `{value}`"));
+        }
+        Ok(combo)
+    }
+}
+
+impl TryFrom<String> for KeyAction {
+    type Error = String;
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let (name, argument) = match value.trim().split_once(char::is_whitespace) {
+            Some((name, argument)) => (name, Some(argument.trim())),
+            None => (value.trim(), None),
+        };
+        let screen = |argument: Option<&str>| -> Result<usize, String> {
+            match argument.and_then(|index| index.parse::<usize>().ok()) {
+                Some(index @ 1..) => Ok(index - 1),
+                _ => Err(format!("`{name}` needs a screen number starting from 1")),
+            }
+        };
+        let gap_delta = |delta: &str| -> Result<i32, String> {
+            delta
+                .parse::<i32>()
+                .map_err(|_| format!("`{name}` needs a number of pixels, got `{delta}`"))
+        };
+        let action = match (name, argument) {
+            ("quit", None) => KeyAction::Quit,
+            ("run", Some(cmd)) => KeyAction::Run(cmd.to_string()),
+            ("toggle-layout", None) => KeyAction::ChangeWmState,
+            ("cycle-layout", None) => KeyAction::CycleLayout,
+            ("grow-master", None) => KeyAction::GrowMaster,
+            ("shrink-master", None) => KeyAction::ShrinkMaster,
+            ("more-masters", None) => KeyAction::MoreMasters,
+            ("fewer-masters", None) => KeyAction::FewerMasters,
+            ("split", Some("h")) => KeyAction::ChangeSplitSate(SplitState::H),
+            ("split", Some("v")) => KeyAction::ChangeSplitSate(SplitState::V),
+            ("screen", _) => KeyAction::Screen(screen(argument)?),
+            ("move-to-screen", _) => KeyAction::MoveToScreen(screen(argument)?),
+            ("focus", Some(direction)) => KeyAction::Focus(Direction::try_from(direction)?),
+            ("swap", Some(direction)) => KeyAction::Swap(Direction::try_from(direction)?),
+            ("move", Some(direction)) => KeyAction::Move(Direction::try_from(direction)?),
+            ("promote", None) => KeyAction::Promote,
+            ("inner-gaps", Some(delta)) => KeyAction::InnerGaps(gap_delta(delta)?),
+            ("outer-gaps", Some(delta)) => KeyAction::OuterGaps(gap_delta(delta)?),
+            ("container", Some(mode)) => KeyAction::Container(ContainerMode::try_from(mode)?),
+            ("toggle-fullscreen", None) => KeyAction::ToggleFullscreen,
+            ("toggle-maximize", None) => KeyAction::ToggleMaximize,
+            ("toggle-floating", None) => KeyAction::ToggleFloating,
+            ("scale-up", None) => KeyAction::ScaleUp,
+            ("scale-down", None) => KeyAction::ScaleDown,
+            ("toggle-preview", None) => KeyAction::TogglePreview,
+            ("rotate-output", None) => KeyAction::RotateOutput,
+            ("toggle-tint", None) => KeyAction::ToggleTint,
+            ("reload-config", None) => KeyAction::ReloadConfig,
+            ("none", None) => KeyAction::None,
+            _ => return Err(format!("unknown action `{value}`")),
+        };
+        Ok(action)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LayoutConfig {
+    /// the mode every workspace starts in
+    pub default: WmStatus,
+    pub split: SplitState,
+    /// the part of the width taken by the masters in master mode
+    pub master_ratio: f64,
+    /// how many windows are masters in master mode
+    pub master_count: usize,
+    /// the part of the width taken by a column in scroll mode
+    pub column_width: f64,
+    pub gaps: GapsConfig,
+}
+
+/// The space left around the tiles
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GapsConfig {
+    /// between two tiles, in logical pixels
+    pub inner: i32,
+    /// between the tiles and the edges of the usable area
+    pub outer: i32,
+    /// no gaps around a window shown alone
+    pub smart: bool,
+}
+
+impl Default for GapsConfig {
+    fn default() -> Self {
+        Self {
+            inner: 0,
+            outer: 0,
+            smart: true,
+        }
+    }
+}
+
+impl Default for LayoutConfig {
+    fn default() -> Self {
+        Self {
+            default: WmStatus::Tile,
+            split: SplitState::H,
+            master_ratio: 0.55,
+            master_count: 1,
+            column_width: 0.5,
+            gaps: GapsConfig::default(),
+        }
+    }
+}
+
+/// A colour written `#rrggbb` or `#rrggbbaa`
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Color(pub [f32; 4]);
+
+impl TryFrom<String> for Color {
+    type Error = String;
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let Some(hex) = value
+            .strip_prefix('#')
+            .filter(|hex| matches!(hex.len(), 6 | 8))
+            .filter(|hex| hex.chars().all(|c| c.is_ascii_hexdigit()))
+        else {
+            return Err(format!(
+                "invalid colour `{value}`, expected #rrggbb or #rrggbbaa"
+            ));
+        };
+        let mut color = [1.0; 4];
+        for (index, channel) in color.iter_mut().take(hex.len() / 2).enumerate() {
+            let byte = u8::from_str_radix(&hex[index * 2..index * 2 + 2], 16).unwrap();
+            *channel = byte as f32 / 255.0;
+        }
+        Ok(Color(color))
+    }
+}
+
+/// The border drawn around the windows
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BorderConfig {
+    /// width in logical pixels, 0 draws no border
+    pub width: i32,
+    pub focused: Color,
+    pub unfocused: Color,
+}
+
+impl Default for BorderConfig {
+    fn default() -> Self {
+        Self {
+            width: 2,
+            focused: Color([0.37, 0.51, 0.67, 1.0]),
+            unfocused: Color([0.23, 0.26, 0.32, 1.0]),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct InputConfig {
+    pub repeat_delay: i32,
+    pub repeat_rate: i32,
+    pub xkb_rules: String,
+    pub xkb_model: String,
+    pub xkb_layout: String,
+    pub xkb_variant: String,
+    pub xkb_options: Option<String>,
+    /// the window under the pointer takes the keyboard
+    pub focus_follows_mouse: bool,
+}
+
+impl Default for InputConfig {
+    fn default() -> Self {
+        Self {
+            repeat_delay: 200,
+            repeat_rate: 200,
+            xkb_rules: String::new(),
+            xkb_model: String::new(),
+            xkb_layout: String::new(),
+            xkb_variant: String::new(),
+            xkb_options: None,
+            focus_follows_mouse: false,
+        }
+    }
+}
+
+impl InputConfig {
+    pub fn xkb_config(&self) -> XkbConfig<'_> {
+        XkbConfig {
+            rules: &self.xkb_rules,
+            model: &self.xkb_model,
+            layout: &self.xkb_layout,
+            variant: &self.xkb_variant,
+            options: self.xkb_options.clone(),
+        }
+    }
+}
+
+/// The config read from `$XDG_CONFIG_HOME/flyja/config.toml`
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// commands run once the compositor is ready
+    pub startup: Vec<String>,
+    pub layout: LayoutConfig,
+    pub input: InputConfig,
+    pub border: BorderConfig,
+    /// applied in their order to the windows they match
+    pub rules: Vec<WindowRule>,
+    /// counts the reloads, the windows keep the indices of the rules they
+    /// matched along with it
+    #[serde(skip)]
+    pub generation: u64,
+    /// bindings on top of the built-in ones, `none` removes a built-in one
+    bindings: HashMap<KeyCombo, KeyAction>,
+}
+
+const DEFAULT_BINDINGS: &[(&str, &str)] = &[
+    ("Ctrl+Alt+BackSpace", "quit"),
+    ("Logo+q", "quit"),
+    ("Logo+Return", "run wezterm"),
+    ("Logo+Shift+M", "scale-down"),
+    ("Logo+Shift+P", "toggle-layout"),
+    ("Logo+space", "cycle-layout"),
+    ("Logo+bracketright", "grow-master"),
+    ("Logo+bracketleft", "shrink-master"),
+    ("Logo+i", "more-masters"),
+    ("Logo+d", "fewer-masters"),
+    ("Logo+Shift+W", "toggle-preview"),
+    ("Logo+Shift+R", "rotate-output"),
+    ("Logo+Shift+T", "toggle-tint"),
+    ("Logo+v", "split v"),
+    ("Logo+b", "split h"),
+    ("Logo+h", "focus left"),
+    ("Logo+j", "focus down"),
+    ("Logo+k", "focus up"),
+    ("Logo+l", "focus right"),
+    ("Logo+Ctrl+h", "swap left"),
+    ("Logo+Ctrl+j", "swap down"),
+    ("Logo+Ctrl+k", "swap up"),
+    ("Logo+Ctrl+l", "swap right"),
+    ("Logo+Shift+H", "move left"),
+    ("Logo+Shift+J", "move down"),
+    ("Logo+Shift+K", "move up"),
+    ("Logo+Shift+L", "move right"),
+    ("Logo+Shift+Return", "promote"),
+    ("Logo+e", "container split"),
+    ("Logo+w", "container tabbed"),
+    ("Logo+s", "container stacked"),
+    ("Logo+equal", "inner-gaps 5"),
+    ("Logo+minus", "inner-gaps -5"),
+    ("Logo+Shift+plus", "outer-gaps 5"),
+    ("Logo+Shift+underscore", "outer-gaps -5"),
+    ("Logo+f", "toggle-fullscreen"),
+    ("Logo+m", "toggle-maximize"),
+    ("Logo+Shift+space", "toggle-floating"),
+    ("Logo+Shift+C", "reload-config"),
+];
+
+fn default_bindings() -> HashMap<KeyCombo, KeyAction> {
+    let screens = (1..=9).flat_map(|index| {
+        [
+            (format!("Logo+{index}"), format!("screen {index}")),
+            (
+                format!("Logo+Shift+{index}"),
+                format!("move-to-screen {index}"),
+            ),
+        ]
+    });
+    DEFAULT_BINDINGS
+        .iter()
+        .map(|(keys, action)| (keys.to_string(), action.to_string()))
+        .chain(screens)
+        .map(|(keys, action)| {
+            (
+                KeyCombo::try_from(keys).unwrap(),
+                KeyAction::try_from(action).unwrap(),
+            )
+        })
+        .collect()
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            startup: Vec::new(),
+            layout: LayoutConfig::default(),
+            input: InputConfig::default(),
+            border: BorderConfig::default(),
+            rules: Vec::new(),
+            generation: 0,
+            bindings: default_bindings(),
+        }
+    }
+}
+
+impl Config {
+    pub fn path() -> Option<PathBuf> {
+        let config_home = std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+        Some(config_home.join("flyja").join("config.toml"))
+    }
+
+    /// Read the config file, the built-in defaults are used if it does not
+    /// exist or cannot be parsed
+    pub fn load() -> Self {
+        Self::try_load().unwrap_or_else(|err| {
+            tracing::error!("{}, using the default config", err);
+            Self::default()
+        })
+    }
+
+    /// Read the config file, a missing file gives the built-in defaults
+    pub fn try_load() -> Result<Self, String> {
+        let Some(path) = Self::path() else {
+            tracing::warn!("Cannot find the config directory, using the default config");
+            return Ok(Self::default());
+        };
+        let content = match std::fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                tracing::info!("No config at {}, using the default config", path.display());
+                return Ok(Self::default());
+            }
+            Err(err) => return Err(format!("Cannot read {}: {}", path.display(), err)),
+        };
+        Self::parse(&content).map_err(|err| format!("Error in {}: {}", path.display(), err))
+    }
+
+    /// Parse the content of a config file, the errors point at the line
+    pub fn parse(content: &str) -> Result<Self, toml::de::Error> {
+        let mut config: Self = toml::from_str(content)?;
+        let mut bindings = default_bindings();
+        bindings.extend(config.bindings);
+        config.bindings = bindings;
+        Ok(config)
+    }
+
+    /// The action bound to the keys, the modified keysym is tried first, then
+    /// the raw one so `Logo+Shift+1` can be written instead of `Logo+Shift+exclam`
+    pub fn binding(
+        &self,
+        modifiers: &ModifiersState,
+        keysym: Keysym,
+        raw_keysym: Keysym,
+    ) -> Option<KeyAction> {
+        [keysym, raw_keysym].into_iter().find_map(|keysym| {
+            let combo = KeyCombo::from_modifiers(modifiers, keysym);
+            match self.bindings.get(&combo)? {
+                KeyAction::None => None,
+                action => Some(action.clone()),
+            }
+        })
+    }
+}
+
+/// Watch the config directory, the config is reloaded when config.toml is
+/// written or replaced. The directory is created if needed, so a config
+/// written after the start is picked up
+pub fn init_config_watcher<BackendData: Backend + 'static>(
+    handle: &LoopHandle<'_, CalloopData<BackendData>>,
+) {
+    let Some(dir) = Config::path().and_then(|path| path.parent().map(Path::to_path_buf)) else {
+        return;
+    };
+    if let Err(err) = std::fs::create_dir_all(&dir) {
+        tracing::warn!("Cannot create {}: {}", dir.display(), err);
+        return;
+    }
+    let mut inotify = match Inotify::init() {
+        Ok(inotify) => inotify,
+        Err(err) => {
+            tracing::warn!("Cannot watch the config: {}", err);
+            return;
+        }
+    };
+    if let Err(err) = inotify.add_watch(
+        &dir,
+        WatchMask::CLOSE_WRITE | WatchMask::MOVED_TO | WatchMask::CREATE,
+    ) {
+        tracing::warn!("Cannot watch {}: {}", dir.display(), err);
+        return;
+    }
+    handle
+        .insert_source(
+            Generic::new(inotify, Interest::READ, Mode::Level),
+            |_, inotify, data| {
+                let mut buffer = [0; 1024];
+                let changed = match inotify.read_events(&mut buffer) {
+                    Ok(events) => events
+                        .filter_map(|event| event.name)
+                        .any(|name| name == "config.toml"),
+                    Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => false,
+                    Err(err) => return Err(err),
+                };
+                if changed {
+                    data.state.reload_config();
+                }
+                Ok(PostAction::Continue)
+            },
+        )
+        .expect("Failed to watch the config");
+}
+
+impl<BackendData: Backend + 'static> FlyJa<BackendData> {
+    /// Reload the config file, the current config is kept if the new one is
+    /// invalid
+    pub fn reload_config(&mut self) {
+        match Config::try_load() {
+            Ok(mut config) => {
+                config.generation = self.config.generation + 1;
+                self.config = config;
+                self.apply_config();
+                tracing::info!("Config reloaded");
+            }
+            Err(err) => {
+                tracing::error!("{}, keeping the previous config", err);
+            }
+        }
+    }
+
+    /// Apply the parts of the config which live outside of it
+    pub fn apply_config(&mut self) {
+        let input = self.config.input.clone();
+        let keyboard = self.seat.get_keyboard().unwrap();
+        if let Err(err) = keyboard.set_xkb_config(self, input.xkb_config()) {
+            tracing::error!(?err, "Failed to apply the keymap");
+        }
+        keyboard.change_repeat_info(input.repeat_rate, input.repeat_delay);
+        let windows: Vec<WindowElement> = self
+            .workspaces
+            .iter()
+            .flat_map(|workspace| workspace.windows())
+            .collect();
+        for window in windows.iter() {
+            window.set_border(&self.config.border);
+            // the rules of the new config turn it off again
+            window.set_border_enabled(true);
+        }
+        // the rules are matched again, a reload bumped the generation so
+        // every rule a window matches is applied
+        for window in windows.iter() {
+            self.apply_window_rules(window);
+        }
+        self.apply_layout();
+    }
+}
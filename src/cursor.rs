@@ -0,0 +1,260 @@
+use std::sync::Mutex;
+
+use smithay::{
+    backend::{
+        allocator::Fourcc,
+        renderer::{
+            element::{
+                surface::{render_elements_from_surface_tree, WaylandSurfaceRenderElement},
+                texture::{TextureBuffer, TextureRenderElement},
+                AsRenderElements, Kind,
+            },
+            ImportAll, ImportMem, Renderer, Texture,
+        },
+    },
+    input::pointer::{CursorImageAttributes, CursorImageStatus},
+    render_elements,
+    utils::{IsAlive, Logical, Physical, Point, Scale, Transform},
+    wayland::compositor::with_states,
+};
+use xcursor::{
+    parser::{parse_xcursor, Image},
+    CursorTheme,
+};
+
+const DEFAULT_THEME: &str = "default";
+const DEFAULT_SIZE: u32 = 24;
+
+/// The images of the default cursor of the xcursor theme
+#[derive(Debug)]
+pub struct Cursor {
+    icons: Vec<Image>,
+    size: u32,
+}
+
+impl Cursor {
+    /// Load the theme of `XCURSOR_THEME` at the size of `XCURSOR_SIZE`, a
+    /// plain arrow is drawn when the theme has no default cursor
+    pub fn load() -> Self {
+        let name = std::env::var("XCURSOR_THEME").unwrap_or_else(|_| DEFAULT_THEME.to_string());
+        let size = std::env::var("XCURSOR_SIZE")
+            .ok()
+            .and_then(|size| size.parse().ok())
+            .unwrap_or(DEFAULT_SIZE);
+        let theme = CursorTheme::load(&name);
+        let icons = load_icon(&theme).unwrap_or_else(|err| {
+            tracing::warn!(theme = name, "{}, using the built-in cursor", err);
+            (1..=3).map(|scale| fallback_icon(size * scale)).collect()
+        });
+        Self { icons, size }
+    }
+
+    /// The image with the size closest to the cursor size at the scale,
+    /// the first frame of an animated cursor
+    pub fn image(&self, scale: u32) -> &Image {
+        let size = (self.size * scale) as i64;
+        self.icons
+            .iter()
+            .min_by_key(|image| (size - image.size as i64).abs())
+            .unwrap()
+    }
+}
+
+fn load_icon(theme: &CursorTheme) -> Result<Vec<Image>, String> {
+    let path = theme
+        .load_icon("default")
+        .or_else(|| theme.load_icon("left_ptr"))
+        .ok_or_else(|| "No default cursor in the theme".to_string())?;
+    let content =
+        std::fs::read(&path).map_err(|err| format!("Cannot read {}: {}", path.display(), err))?;
+    parse_xcursor(&content)
+        .filter(|images| !images.is_empty())
+        .ok_or_else(|| format!("Cannot parse {}", path.display()))
+}
+
+/// A white arrow with a black outline
+fn fallback_icon(size: u32) -> Image {
+    let inside = |x: u32, y: u32| y < size && x <= y && 2 * x + y < 3 * size / 2;
+    let mut pixels_rgba = Vec::with_capacity((size * size * 4) as usize);
+    for y in 0..size {
+        for x in 0..size {
+            let pixel = if !inside(x, y) {
+                [0, 0, 0, 0]
+            } else if x == 0 || x == y || !inside(x + 1, y) || !inside(x, y + 1) {
+                [0, 0, 0, 255]
+            } else {
+                [255, 255, 255, 255]
+            };
+            pixels_rgba.extend_from_slice(&pixel);
+        }
+    }
+    Image {
+        size,
+        width: size,
+        height: size,
+        xhot: 0,
+        yhot: 0,
+        delay: 0,
+        pixels_rgba,
+        pixels_argb: Vec::new(),
+    }
+}
+
+/// The default cursor uploaded at an integer scale, with its hotspot
+struct DefaultCursor<T: Texture> {
+    scale: u32,
+    texture: TextureBuffer<T>,
+    hotspot: Point<i32, Logical>,
+}
+
+/// The pointer drawn on top of the outputs, the textures of the default
+/// cursor are kept for the renderer they were uploaded with, one per scale
+pub struct PointerElement<T: Texture> {
+    defaults: Vec<DefaultCursor<T>>,
+    /// the scale of the output drawn next
+    scale: u32,
+    status: CursorImageStatus,
+}
+
+impl<T: Texture> Default for PointerElement<T> {
+    fn default() -> Self {
+        Self {
+            defaults: Vec::new(),
+            scale: 1,
+            status: CursorImageStatus::Default,
+        }
+    }
+}
+
+impl<T: Texture + Clone + 'static> PointerElement<T> {
+    /// Take the image the client asked for, the default cursor is used again
+    /// once the surface of the client is gone. The default cursor is drawn
+    /// from the image of the theme for the scale of the output, rounded up
+    pub fn update<R>(
+        &mut self,
+        renderer: &mut R,
+        cursor: &Cursor,
+        status: &CursorImageStatus,
+        scale: f64,
+    ) where
+        R: Renderer<TextureId = T> + ImportMem,
+    {
+        self.scale = scale.ceil().max(1.0) as u32;
+        if self.default_cursor().is_none() {
+            let image = cursor.image(self.scale);
+            match TextureBuffer::from_memory(
+                renderer,
+                &image.pixels_rgba,
+                Fourcc::Abgr8888,
+                (image.width as i32, image.height as i32),
+                false,
+                self.scale as i32,
+                Transform::Normal,
+                None,
+            ) {
+                Ok(texture) => {
+                    let hotspot = (
+                        (image.xhot / self.scale) as i32,
+                        (image.yhot / self.scale) as i32,
+                    );
+                    self.defaults.push(DefaultCursor {
+                        scale: self.scale,
+                        texture,
+                        hotspot: hotspot.into(),
+                    });
+                }
+                Err(err) => tracing::warn!(?err, "Failed to upload the cursor"),
+            }
+        }
+        self.status = match status {
+            CursorImageStatus::Surface(surface) if !surface.alive() => CursorImageStatus::Default,
+            status => status.clone(),
+        };
+    }
+
+    /// The point of the image which is at the pointer location
+    pub fn hotspot(&self) -> Point<i32, Logical> {
+        match &self.status {
+            CursorImageStatus::Surface(surface) => with_states(surface, |states| {
+                states
+                    .data_map
+                    .get::<Mutex<CursorImageAttributes>>()
+                    .map(|attributes| attributes.lock().unwrap().hotspot)
+                    .unwrap_or_default()
+            }),
+            _ => self
+                .default_cursor()
+                .map(|default| default.hotspot)
+                .unwrap_or_default(),
+        }
+    }
+
+    fn default_cursor(&self) -> Option<&DefaultCursor<T>> {
+        self.defaults
+            .iter()
+            .find(|default| default.scale == self.scale)
+    }
+}
+
+render_elements!(
+    pub PointerRenderElement<R> where R: ImportAll;
+    Surface=WaylandSurfaceRenderElement<R>,
+    Texture=TextureRenderElement<<R as Renderer>::TextureId>,
+);
+
+impl<R: Renderer> std::fmt::Debug for PointerRenderElement<R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Surface(arg0) => f.debug_tuple("Surface").field(arg0).finish(),
+            Self::Texture(arg0) => f.debug_tuple("Texture").field(arg0).finish(),
+            Self::_GenericCatcher(arg0) => f.debug_tuple("_GenericCatcher").field(arg0).finish(),
+        }
+    }
+}
+
+impl<T, R> AsRenderElements<R> for PointerElement<T>
+where
+    T: Texture + Clone + 'static,
+    R: Renderer<TextureId = T> + ImportAll,
+{
+    type RenderElement = PointerRenderElement<R>;
+
+    fn render_elements<C: From<PointerRenderElement<R>>>(
+        &self,
+        renderer: &mut R,
+        location: Point<i32, Physical>,
+        scale: Scale<f64>,
+        alpha: f32,
+    ) -> Vec<C> {
+        match &self.status {
+            CursorImageStatus::Hidden => Vec::new(),
+            CursorImageStatus::Default => self
+                .default_cursor()
+                .into_iter()
+                .map(|default| {
+                    C::from(PointerRenderElement::from(
+                        TextureRenderElement::from_texture_buffer(
+                            location.to_f64(),
+                            &default.texture,
+                            Some(alpha),
+                            None,
+                            None,
+                            Kind::Cursor,
+                        ),
+                    ))
+                })
+                .collect(),
+            CursorImageStatus::Surface(surface) => {
+                let elements: Vec<PointerRenderElement<R>> = render_elements_from_surface_tree(
+                    renderer,
+                    surface,
+                    location,
+                    scale,
+                    alpha,
+                    Kind::Cursor,
+                );
+                elements.into_iter().map(C::from).collect()
+            }
+        }
+    }
+}
@@ -0,0 +1,195 @@
+use smithay::{
+    desktop::space::SpaceElement,
+    reexports::wayland_server::protocol::wl_surface::WlSurface,
+    utils::{Logical, Point, Rectangle, Serial, SERIAL_COUNTER},
+    wayland::shell::wlr_layer::Layer,
+};
+
+use crate::{ipc::protocol::Event, shell::WindowElement, state::Backend, FlyJa};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+impl TryFrom<&str> for Direction {
+    type Error = String;
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "left" => Ok(Direction::Left),
+            "right" => Ok(Direction::Right),
+            "up" => Ok(Direction::Up),
+            "down" => Ok(Direction::Down),
+            _ => Err(format!("unknown direction `{value}`")),
+        }
+    }
+}
+
+impl Direction {
+    /// How far the rectangle is from the edge of the one it is looked for
+    /// from, and how far their centers are across the direction. None when
+    /// it is not in the direction
+    fn distance(
+        self,
+        from: Rectangle<i32, Logical>,
+        to: Rectangle<i32, Logical>,
+    ) -> Option<(i32, i32)> {
+        let center = |rect: Rectangle<i32, Logical>| {
+            (rect.loc.x + rect.size.w / 2, rect.loc.y + rect.size.h / 2)
+        };
+        let (from_x, from_y) = center(from);
+        let (to_x, to_y) = center(to);
+        let (along, across) = match self {
+            Direction::Left => (from.loc.x - (to.loc.x + to.size.w), to_y - from_y),
+            Direction::Right => (to.loc.x - (from.loc.x + from.size.w), to_y - from_y),
+            Direction::Up => (from.loc.y - (to.loc.y + to.size.h), to_x - from_x),
+            Direction::Down => (to.loc.y - (from.loc.y + from.size.h), to_x - from_x),
+        };
+        (along >= 0).then_some((along, across.abs()))
+    }
+}
+
+impl<BackendData: Backend + 'static> FlyJa<BackendData> {
+    pub fn focused_window(&self) -> Option<WindowElement> {
+        let focus = self.seat.get_keyboard()?.current_focus()?;
+        self.window_for_surface(&focus)
+    }
+
+    /// Raise the window and give it the keyboard focus, a tile stays under
+    /// the floating windows
+    pub fn focus_window(&mut self, window: &WindowElement, serial: Serial) {
+        self.space.raise_element(window, false);
+        if self.is_tiled_window(window) {
+            self.raise_floating();
+        }
+        let keyboard = self.seat.get_keyboard().unwrap();
+        keyboard.set_focus(self, Some(window.toplevel().wl_surface().clone()), serial);
+    }
+
+    /// Give the keyboard to the window of the workspace used last, or to the
+    /// one on top of the space
+    pub fn focus_last_window(&mut self) {
+        let serial = SERIAL_COUNTER.next_serial();
+        let window = self
+            .workspace()
+            .last_focused()
+            .filter(|window| self.space.elements().any(|w| w == window))
+            .or_else(|| self.space.elements().last().cloned());
+        if let Some(window) = window {
+            self.focus_window(&window, serial);
+        } else {
+            let keyboard = self.seat.get_keyboard().unwrap();
+            keyboard.set_focus(self, Option::<WlSurface>::None, serial);
+        }
+    }
+
+    /// Keep the activated state of the windows on the keyboard focus, only
+    /// the windows whose state changed are configured
+    pub fn handle_focus_changed(&mut self, focused: Option<&WlSurface>) {
+        let focused = focused.and_then(|surface| self.any_window_for_surface(surface));
+        if let Some(window) = &focused {
+            self.workspace_mut().note_focus(window);
+            // the tab of the window is selected, which maps it again
+            if let Some(area) = self.layout_area() {
+                let gaps = self.config.layout.gaps;
+                if self.workspace_mut().reveal(window, area, &gaps) {
+                    self.apply_layout();
+                }
+            }
+        }
+        for window in self.space.elements() {
+            let changed = window.set_activated(Some(window) == focused.as_ref());
+            if changed && window.initial_configure_sent() {
+                window.toplevel().send_pending_configure();
+            }
+        }
+        let Some(window) = focused else {
+            return;
+        };
+        self.ipc_event(Event::WindowFocused { id: window.id() });
+    }
+
+    /// With focus-follows-mouse, the window under the pointer takes the
+    /// keyboard without being raised
+    pub fn focus_under_pointer(&mut self, pos: Point<f64, Logical>, serial: Serial) {
+        if !self.config.input.focus_follows_mouse
+            || self.pointer.is_grabbed()
+            || self.exclusive_layer().is_some()
+        {
+            return;
+        }
+        let window = match self.fullscreen_window_under(pos) {
+            Some(window) => window,
+            None => {
+                if self
+                    .layer_under(&[Layer::Overlay, Layer::Top], pos)
+                    .is_some()
+                {
+                    return;
+                }
+                let Some((window, _)) = self.space.element_under(pos) else {
+                    return;
+                };
+                window.clone()
+            }
+        };
+        if self.focused_window().as_ref() == Some(&window) {
+            return;
+        }
+        let keyboard = self.seat.get_keyboard().unwrap();
+        keyboard.set_focus(self, Some(window.toplevel().wl_surface().clone()), serial);
+    }
+
+    /// Focus the closest window in the direction, from the edge of the
+    /// focused one. The tiles are used in tile mode, the windows of the
+    /// space otherwise
+    pub fn focus_direction(&mut self, direction: Direction) {
+        let Some(focused) = self.focused_window() else {
+            self.focus_last_window();
+            return;
+        };
+        if let Some(window) = self.window_in_direction(&focused, direction) {
+            self.focus_window(&window, SERIAL_COUNTER.next_serial());
+        }
+    }
+
+    /// The closest window in the direction, from the edge of the window
+    pub fn window_in_direction(
+        &self,
+        window: &WindowElement,
+        direction: Direction,
+    ) -> Option<WindowElement> {
+        let geometries = self.focus_geometries();
+        let from = geometries
+            .iter()
+            .find(|(w, _)| w == window)
+            .map(|(_, geometry)| *geometry)?;
+        geometries
+            .into_iter()
+            .filter(|(w, _)| w != window)
+            .filter_map(|(w, geometry)| Some((direction.distance(from, geometry)?, w)))
+            .min_by_key(|(distance, _)| *distance)
+            .map(|(_, w)| w)
+    }
+
+    fn focus_geometries(&self) -> Vec<(WindowElement, Rectangle<i32, Logical>)> {
+        if self.workspace().wmstatus.is_tiled() {
+            if let Some(arrangement) = self.arrangement() {
+                return arrangement.tiles;
+            }
+        }
+        self.space
+            .elements()
+            .filter_map(|window| {
+                let location = self.space.element_location(window)?;
+                Some((
+                    window.clone(),
+                    Rectangle::from_loc_and_size(location, window.geometry().size),
+                ))
+            })
+            .collect()
+    }
+}
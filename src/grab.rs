@@ -1 +1,2 @@
 pub mod move_grab;
+pub mod resize_grab;
@@ -13,6 +13,8 @@ pub struct MoveSurfaceGrab<BackendData: Backend + 'static> {
     pub start_data: GrabStartData<FlyJa<BackendData>>,
     pub window: WindowElement,
     pub initial_window_location: Point<i32, Logical>,
+    /// a tiled window is swapped with the tile it is dropped on
+    pub tiled: bool,
 }
 
 impl<BackendData: Backend> PointerGrab<FlyJa<BackendData>> for MoveSurfaceGrab<BackendData> {
@@ -62,6 +64,9 @@ impl<BackendData: Backend> PointerGrab<FlyJa<BackendData>> for MoveSurfaceGrab<B
         if !handle.current_pressed().contains(&BTN_LEFT) {
             // No more buttons are pressed, release the grab.
             handle.unset_grab(data, event.serial, event.time);
+            if self.tiled {
+                self.drop_tile(data, handle.current_location());
+            }
         }
     }
 
@@ -69,3 +74,23 @@ impl<BackendData: Backend> PointerGrab<FlyJa<BackendData>> for MoveSurfaceGrab<B
         &self.start_data
     }
 }
+
+impl<BackendData: Backend> MoveSurfaceGrab<BackendData> {
+    /// Swap the window with the tile under location, the tiles are laid
+    /// out again either way so the window goes back to a tile
+    fn drop_tile(&self, data: &mut FlyJa<BackendData>, location: Point<f64, Logical>) {
+        if let Some(arrangement) = data.arrangement() {
+            let target = arrangement
+                .tiles
+                .into_iter()
+                .find(|(window, geometry)| {
+                    window != &self.window && geometry.to_f64().contains(location)
+                })
+                .map(|(window, _)| window);
+            if let Some(target) = target {
+                data.workspace_mut().layout.swap(&self.window, &target);
+            }
+        }
+        data.apply_layout();
+    }
+}
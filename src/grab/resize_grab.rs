@@ -0,0 +1,327 @@
+use std::cell::RefCell;
+
+use crate::{
+    shell::WindowElement,
+    state::{Backend, SplitState, WmStatus},
+    FlyJa,
+};
+
+use smithay::{
+    desktop::space::SpaceElement,
+    input::pointer::{GrabStartData, PointerGrab, PointerInnerHandle},
+    reexports::{
+        wayland_protocols::xdg::shell::server::xdg_toplevel,
+        wayland_server::protocol::wl_surface::WlSurface,
+    },
+    utils::{Logical, Point, Serial, Size},
+    wayland::{compositor::with_states, shell::xdg::SurfaceCachedState},
+};
+
+bitflags::bitflags! {
+    /// The edges of a window, the values match `xdg_toplevel::ResizeEdge`
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct ResizeEdge: u32 {
+        const TOP = 0b0001;
+        const BOTTOM = 0b0010;
+        const LEFT = 0b0100;
+        const RIGHT = 0b1000;
+
+        const TOP_LEFT = Self::TOP.bits() | Self::LEFT.bits();
+        const BOTTOM_LEFT = Self::BOTTOM.bits() | Self::LEFT.bits();
+
+        const TOP_RIGHT = Self::TOP.bits() | Self::RIGHT.bits();
+        const BOTTOM_RIGHT = Self::BOTTOM.bits() | Self::RIGHT.bits();
+    }
+}
+
+impl From<xdg_toplevel::ResizeEdge> for ResizeEdge {
+    fn from(value: xdg_toplevel::ResizeEdge) -> Self {
+        Self::from_bits_truncate(value as u32)
+    }
+}
+
+/// Where a floating window was when the resize started
+#[derive(Debug, Clone, Copy)]
+pub struct ResizeData {
+    edges: ResizeEdge,
+    initial_window_location: Point<i32, Logical>,
+    initial_window_size: Size<i32, Logical>,
+}
+
+/// Kept in the data of the surface, the window is moved on commit so the
+/// edges opposite to the dragged ones stay in place
+#[derive(Debug, Default, Clone, Copy)]
+enum ResizeState {
+    #[default]
+    NotResizing,
+    Resizing(ResizeData),
+    /// the grab is over, the configure of the final size with its serial is
+    /// not acked yet
+    WaitingForFinalAck(ResizeData, Serial),
+    /// the final configure is acked, the next commit has the final size
+    WaitingForFinalCommit(ResizeData),
+}
+
+fn set_resize_state(surface: &WlSurface, state: ResizeState) {
+    with_states(surface, |states| {
+        states
+            .data_map
+            .insert_if_missing(RefCell::<ResizeState>::default);
+        *states
+            .data_map
+            .get::<RefCell<ResizeState>>()
+            .unwrap()
+            .borrow_mut() = state;
+    });
+}
+
+fn resize_state(surface: &WlSurface) -> ResizeState {
+    with_states(surface, |states| {
+        states
+            .data_map
+            .get::<RefCell<ResizeState>>()
+            .map(|state| *state.borrow())
+            .unwrap_or_default()
+    })
+}
+
+/// Resize a window with the pointer. A floating window is resized freely
+/// within its min and max size, a tiled one moves the split between it and
+/// its neighbours
+#[derive(Debug)]
+pub struct ResizeSurfaceGrab<BackendData: Backend + 'static> {
+    start_data: GrabStartData<FlyJa<BackendData>>,
+    window: WindowElement,
+    edges: ResizeEdge,
+    tiled: bool,
+    initial_window_size: Size<i32, Logical>,
+    last_window_size: Size<i32, Logical>,
+    last_location: Point<f64, Logical>,
+}
+
+impl<BackendData: Backend> ResizeSurfaceGrab<BackendData> {
+    pub fn new(
+        start_data: GrabStartData<FlyJa<BackendData>>,
+        window: WindowElement,
+        edges: ResizeEdge,
+        initial_window_location: Point<i32, Logical>,
+        tiled: bool,
+    ) -> Self {
+        let initial_window_size = window.geometry().size;
+        if !tiled {
+            set_resize_state(
+                window.toplevel().wl_surface(),
+                ResizeState::Resizing(ResizeData {
+                    edges,
+                    initial_window_location,
+                    initial_window_size,
+                }),
+            );
+        }
+        Self {
+            last_location: start_data.location,
+            start_data,
+            window,
+            edges,
+            tiled,
+            initial_window_size,
+            last_window_size: initial_window_size,
+        }
+    }
+
+    fn resize_floating(&mut self, location: Point<f64, Logical>) {
+        let (mut dx, mut dy) = (location - self.start_data.location).into();
+
+        let mut new_width = self.initial_window_size.w;
+        let mut new_height = self.initial_window_size.h;
+
+        if self.edges.intersects(ResizeEdge::LEFT | ResizeEdge::RIGHT) {
+            if self.edges.intersects(ResizeEdge::LEFT) {
+                dx = -dx;
+            }
+            new_width = (self.initial_window_size.w as f64 + dx) as i32;
+        }
+        if self.edges.intersects(ResizeEdge::TOP | ResizeEdge::BOTTOM) {
+            if self.edges.intersects(ResizeEdge::TOP) {
+                dy = -dy;
+            }
+            new_height = (self.initial_window_size.h as f64 + dy) as i32;
+        }
+
+        let (min_size, max_size) = with_states(self.window.toplevel().wl_surface(), |states| {
+            let data = states.cached_state.current::<SurfaceCachedState>();
+            (data.min_size, data.max_size)
+        });
+        // the sizes asked by the client do not count the border
+        let border = 2 * self.window.border_width();
+        let min_width = min_size.w.max(1) + border;
+        let min_height = min_size.h.max(1) + border;
+        let max_width = match max_size.w {
+            0 => i32::MAX,
+            width => (width + border).max(min_width),
+        };
+        let max_height = match max_size.h {
+            0 => i32::MAX,
+            height => (height + border).max(min_height),
+        };
+        self.last_window_size = (
+            new_width.clamp(min_width, max_width),
+            new_height.clamp(min_height, max_height),
+        )
+            .into();
+
+        let toplevel = self.window.toplevel();
+        toplevel.with_pending_state(|state| {
+            state.states.set(xdg_toplevel::State::Resizing);
+            state.size = Some(self.window.content_size(self.last_window_size));
+        });
+        toplevel.send_pending_configure();
+    }
+
+    fn resize_tiled(&mut self, data: &mut FlyJa<BackendData>, location: Point<f64, Logical>) {
+        let delta = location - self.last_location;
+        self.last_location = location;
+        let Some(area) = data.layout_area() else {
+            return;
+        };
+        if data.workspace().wmstatus == WmStatus::Scroll {
+            // the columns all have the width of the config
+            return;
+        }
+        if data.workspace().wmstatus == WmStatus::Master {
+            // only the edge between the masters and the stack moves
+            if self.edges.intersects(ResizeEdge::LEFT | ResizeEdge::RIGHT) {
+                let ratio = data.workspace().master_ratio + delta.x / area.size.w as f64;
+                data.workspace_mut().set_master_ratio(ratio);
+                data.apply_layout();
+            }
+            return;
+        }
+        let layout = &mut data.workspace_mut().layout;
+        if self.edges.intersects(ResizeEdge::LEFT | ResizeEdge::RIGHT) {
+            let after = self.edges.contains(ResizeEdge::RIGHT);
+            layout.resize(area, &self.window, SplitState::H, after, delta.x);
+        }
+        if self.edges.intersects(ResizeEdge::TOP | ResizeEdge::BOTTOM) {
+            let after = self.edges.contains(ResizeEdge::BOTTOM);
+            layout.resize(area, &self.window, SplitState::V, after, delta.y);
+        }
+        data.apply_layout();
+    }
+}
+
+impl<BackendData: Backend> PointerGrab<FlyJa<BackendData>> for ResizeSurfaceGrab<BackendData> {
+    fn motion(
+        &mut self,
+        data: &mut FlyJa<BackendData>,
+        handle: &mut PointerInnerHandle<'_, FlyJa<BackendData>>,
+        _focus: Option<(WlSurface, Point<i32, Logical>)>,
+        event: &smithay::input::pointer::MotionEvent,
+    ) {
+        handle.motion(data, None, event);
+
+        if self.tiled {
+            self.resize_tiled(data, event.location);
+        } else {
+            self.resize_floating(event.location);
+        }
+    }
+
+    fn relative_motion(
+        &mut self,
+        data: &mut FlyJa<BackendData>,
+        handle: &mut PointerInnerHandle<'_, FlyJa<BackendData>>,
+        focus: Option<(WlSurface, Point<i32, Logical>)>,
+        event: &smithay::input::pointer::RelativeMotionEvent,
+    ) {
+        handle.relative_motion(data, focus, event)
+    }
+
+    fn axis(
+        &mut self,
+        data: &mut FlyJa<BackendData>,
+        handle: &mut PointerInnerHandle<'_, FlyJa<BackendData>>,
+        details: smithay::input::pointer::AxisFrame,
+    ) {
+        handle.axis(data, details)
+    }
+
+    fn button(
+        &mut self,
+        data: &mut FlyJa<BackendData>,
+        handle: &mut PointerInnerHandle<'_, FlyJa<BackendData>>,
+        event: &smithay::input::pointer::ButtonEvent,
+    ) {
+        handle.button(data, event);
+        if handle.current_pressed().contains(&self.start_data.button) {
+            return;
+        }
+        // No more buttons are pressed, release the grab.
+        handle.unset_grab(data, event.serial, event.time);
+        if self.tiled {
+            return;
+        }
+
+        let toplevel = self.window.toplevel();
+        toplevel.with_pending_state(|state| {
+            state.states.unset(xdg_toplevel::State::Resizing);
+            state.size = Some(self.window.content_size(self.last_window_size));
+        });
+        let serial = toplevel.send_pending_configure();
+
+        if let ResizeState::Resizing(resize_data) = resize_state(toplevel.wl_surface()) {
+            let state = match serial {
+                Some(serial) => ResizeState::WaitingForFinalAck(resize_data, serial),
+                None => ResizeState::WaitingForFinalCommit(resize_data),
+            };
+            set_resize_state(toplevel.wl_surface(), state);
+        }
+    }
+
+    fn start_data(&self) -> &GrabStartData<FlyJa<BackendData>> {
+        &self.start_data
+    }
+}
+
+/// The commits after the ack of the final configure of a resize have the
+/// final size, the ones before belong to the earlier configures
+pub fn handle_resize_ack(surface: &WlSurface, serial: Serial) {
+    if let ResizeState::WaitingForFinalAck(resize_data, final_serial) = resize_state(surface) {
+        if serial >= final_serial {
+            set_resize_state(surface, ResizeState::WaitingForFinalCommit(resize_data));
+        }
+    }
+}
+
+impl<BackendData: Backend + 'static> FlyJa<BackendData> {
+    /// Keep the bottom and right edges in place when a floating window is
+    /// resized from the top or the left
+    pub fn handle_resize_commit(&mut self, surface: &WlSurface) {
+        let (resize_data, finished) = match resize_state(surface) {
+            ResizeState::NotResizing => return,
+            ResizeState::Resizing(resize_data)
+            | ResizeState::WaitingForFinalAck(resize_data, _) => (resize_data, false),
+            ResizeState::WaitingForFinalCommit(resize_data) => (resize_data, true),
+        };
+        if finished {
+            set_resize_state(surface, ResizeState::NotResizing);
+        }
+        let Some(window) = self.window_for_surface(surface) else {
+            return;
+        };
+        let Some(mut location) = self.space.element_location(&window) else {
+            return;
+        };
+        let size = window.geometry().size;
+        let initial = resize_data.initial_window_location;
+        let initial_size = resize_data.initial_window_size;
+
+        if resize_data.edges.intersects(ResizeEdge::LEFT) {
+            location.x = initial.x + (initial_size.w - size.w);
+        }
+        if resize_data.edges.intersects(ResizeEdge::TOP) {
+            location.y = initial.y + (initial_size.h - size.h);
+        }
+        self.space.map_element(window, location, false);
+    }
+}
@@ -1,4 +1,6 @@
 mod compositor;
+mod decoration;
+mod layer_shell;
 mod xdg_shell;
 use smithay::{
     delegate_data_device, delegate_output, delegate_seat,
@@ -18,14 +20,16 @@ impl<BackendData: Backend> SeatHandler for FlyJa<BackendData> {
     fn cursor_image(
         &mut self,
         _seat: &smithay::input::Seat<Self>,
-        _image: smithay::input::pointer::CursorImageStatus,
+        image: smithay::input::pointer::CursorImageStatus,
     ) {
+        self.cursor_status = image;
     }
     fn focus_changed(
         &mut self,
         _seat: &smithay::input::Seat<Self>,
-        _focused: Option<&Self::KeyboardFocus>,
+        focused: Option<&Self::KeyboardFocus>,
     ) {
+        self.handle_focus_changed(focused);
     }
 }
 delegate_seat!(@<BackendData: Backend + 'static> FlyJa<BackendData>);
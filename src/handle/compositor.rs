@@ -13,7 +13,7 @@ use smithay::{
 };
 
 use crate::{
-    state::{Backend, ClientState, WmStatus},
+    state::{Backend, ClientState},
     FlyJa,
 };
 
@@ -33,33 +33,17 @@ impl<BackendData: Backend> CompositorHandler for FlyJa<BackendData> {
             while let Some(parent) = get_parent(&root) {
                 root = parent;
             }
-            if let Some(window) = self
-                .space
-                .elements()
-                .find(|w| w.toplevel().wl_surface() == &root)
-            {
+            if let Some(window) = self.any_window_for_surface(&root) {
                 window.on_commit();
             }
         }
 
         self.handle_window_commit(surface);
+        self.handle_resize_commit(surface);
         self.handle_popup_commit(surface);
+        self.handle_layer_commit(surface);
 
         self.popups.commit(surface);
-
-        // TODO: need know the geo before put it to center
-        // if self.wmstatus == WmStatus::Stack {
-        //     self.handle_place_stack_to_center();
-        // }
-
-        // Tile
-        if self.wmstatus == WmStatus::Tile {
-            self.handle_window_mul_removed_finished();
-            //self.handle_window_mul_removed_finished_check();
-            self.handle_resize_tile_window_changing();
-            self.handle_resize_tile_window_finished();
-            self.handle_resize_tile_split_window_finished();
-        }
     }
 }
 
@@ -0,0 +1,37 @@
+use smithay::{
+    delegate_xdg_decoration,
+    reexports::wayland_protocols::xdg::decoration::zv1::server::zxdg_toplevel_decoration_v1::Mode,
+    wayland::shell::xdg::{decoration::XdgDecorationHandler, ToplevelSurface},
+};
+
+use crate::{
+    shell::initial_configure_sent,
+    state::{Backend, FlyJa},
+};
+
+/// flyja draws the border of every window, so the clients are always asked
+/// to leave their decorations out
+fn use_server_side(toplevel: &ToplevelSurface) {
+    toplevel.with_pending_state(|state| {
+        state.decoration_mode = Some(Mode::ServerSide);
+    });
+    if initial_configure_sent(toplevel) {
+        toplevel.send_pending_configure();
+    }
+}
+
+impl<BackendData: Backend> XdgDecorationHandler for FlyJa<BackendData> {
+    fn new_decoration(&mut self, toplevel: ToplevelSurface) {
+        use_server_side(&toplevel);
+    }
+
+    fn request_mode(&mut self, toplevel: ToplevelSurface, _mode: Mode) {
+        use_server_side(&toplevel);
+    }
+
+    fn unset_mode(&mut self, toplevel: ToplevelSurface) {
+        use_server_side(&toplevel);
+    }
+}
+
+delegate_xdg_decoration!(@<BackendData: Backend + 'static> FlyJa<BackendData>);
@@ -0,0 +1,166 @@
+use smithay::{
+    delegate_layer_shell,
+    desktop::{layer_map_for_output, LayerSurface, WindowSurfaceType},
+    output::Output,
+    reexports::wayland_server::protocol::{wl_output::WlOutput, wl_surface::WlSurface},
+    utils::{Logical, Point, SERIAL_COUNTER},
+    wayland::{
+        compositor::with_states,
+        shell::wlr_layer::{
+            KeyboardInteractivity, Layer, LayerSurface as WlrLayerSurface, LayerSurfaceData,
+            WlrLayerShellHandler, WlrLayerShellState,
+        },
+    },
+};
+
+use crate::state::{Backend, FlyJa};
+
+impl<BackendData: Backend> WlrLayerShellHandler for FlyJa<BackendData> {
+    fn shell_state(&mut self) -> &mut WlrLayerShellState {
+        &mut self.layer_shell_state
+    }
+
+    fn new_layer_surface(
+        &mut self,
+        surface: WlrLayerSurface,
+        wl_output: Option<WlOutput>,
+        _layer: Layer,
+        namespace: String,
+    ) {
+        let output = wl_output
+            .as_ref()
+            .and_then(Output::from_resource)
+            .or_else(|| self.space.outputs().next().cloned());
+        let Some(output) = output else {
+            surface.send_close();
+            return;
+        };
+        let mut map = layer_map_for_output(&output);
+        if let Err(err) = map.map_layer(&LayerSurface::new(surface, namespace)) {
+            tracing::warn!(?err, "Failed to map the layer surface");
+        }
+    }
+
+    fn layer_destroyed(&mut self, surface: WlrLayerSurface) {
+        let focused = self
+            .seat
+            .get_keyboard()
+            .and_then(|keyboard| keyboard.current_focus())
+            .map_or(false, |focus| &focus == surface.wl_surface());
+        for output in self.space.outputs() {
+            let mut map = layer_map_for_output(output);
+            let layer = map
+                .layers()
+                .find(|layer| layer.layer_surface() == &surface)
+                .cloned();
+            if let Some(layer) = layer {
+                map.unmap_layer(&layer);
+            }
+        }
+        // the exclusive zone is given back to the windows
+        self.apply_layout();
+        if focused {
+            self.focus_last_window();
+        }
+    }
+}
+
+delegate_layer_shell!(@<BackendData: Backend + 'static> FlyJa<BackendData>);
+
+impl<BackendData: Backend + 'static> FlyJa<BackendData> {
+    /// Send the initial configure and lay the surfaces of the output out
+    /// again, the windows follow when their exclusive zones changed
+    pub fn handle_layer_commit(&mut self, surface: &WlSurface) {
+        let Some(output) = self
+            .space
+            .outputs()
+            .find(|output| {
+                layer_map_for_output(output)
+                    .layer_for_surface(surface, WindowSurfaceType::TOPLEVEL)
+                    .is_some()
+            })
+            .cloned()
+        else {
+            return;
+        };
+        let initial_configure_sent = with_states(surface, |states| {
+            states
+                .data_map
+                .get::<LayerSurfaceData>()
+                .unwrap()
+                .lock()
+                .unwrap()
+                .initial_configure_sent
+        });
+        let (layer, zone_changed) = {
+            let mut map = layer_map_for_output(&output);
+            let zone = map.non_exclusive_zone();
+            map.arrange();
+            (
+                map.layer_for_surface(surface, WindowSurfaceType::TOPLEVEL)
+                    .cloned(),
+                map.non_exclusive_zone() != zone,
+            )
+        };
+        if zone_changed {
+            self.apply_layout();
+        }
+        let Some(layer) = layer else {
+            return;
+        };
+        if !initial_configure_sent {
+            layer.layer_surface().send_configure();
+            return;
+        }
+
+        let state = layer.cached_state();
+        if state.keyboard_interactivity == KeyboardInteractivity::Exclusive
+            && matches!(state.layer, Layer::Top | Layer::Overlay)
+        {
+            let keyboard = self.seat.get_keyboard().unwrap();
+            if keyboard.current_focus().as_ref() != Some(surface) {
+                keyboard.set_focus(self, Some(surface.clone()), SERIAL_COUNTER.next_serial());
+            }
+        }
+    }
+
+    /// The layer surface taking the keyboard for itself, if any
+    pub fn exclusive_layer(&self) -> Option<LayerSurface> {
+        self.space.outputs().find_map(|output| {
+            layer_map_for_output(output)
+                .layers()
+                .find(|layer| {
+                    let state = layer.cached_state();
+                    state.keyboard_interactivity == KeyboardInteractivity::Exclusive
+                        && matches!(state.layer, Layer::Top | Layer::Overlay)
+                })
+                .cloned()
+        })
+    }
+
+    /// The topmost layer surface under the position in the layers, which
+    /// are searched in order
+    pub fn layer_under(&self, layers: &[Layer], pos: Point<f64, Logical>) -> Option<LayerSurface> {
+        let output = self.space.output_under(pos).next()?;
+        let output_loc = self.space.output_geometry(output)?.loc;
+        let map = layer_map_for_output(output);
+        layers
+            .iter()
+            .find_map(|layer| map.layer_under(*layer, pos - output_loc.to_f64()).cloned())
+    }
+
+    /// The surface of a layer surface under the position, and its location
+    pub fn layer_surface_under(
+        &self,
+        layers: &[Layer],
+        pos: Point<f64, Logical>,
+    ) -> Option<(WlSurface, Point<i32, Logical>)> {
+        let layer = self.layer_under(layers, pos)?;
+        let output = self.space.output_under(pos).next()?;
+        let output_loc = self.space.output_geometry(output)?.loc;
+        let layer_loc = layer_map_for_output(output).layer_geometry(&layer)?.loc + output_loc;
+        layer
+            .surface_under(pos - layer_loc.to_f64(), WindowSurfaceType::ALL)
+            .map(|(surface, loc)| (surface, loc + layer_loc))
+    }
+}
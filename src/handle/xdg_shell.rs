@@ -5,27 +5,29 @@ use smithay::{
         pointer::{Focus, GrabStartData},
         Seat,
     },
+    output::Output,
     reexports::{
         wayland_protocols::xdg::shell::server::xdg_toplevel,
         wayland_server::{
-            protocol::{wl_seat, wl_surface},
+            protocol::{wl_output, wl_seat, wl_surface},
             Resource,
         },
     },
-    utils::{Point, Serial, Size},
+    utils::{Logical, Rectangle, Serial},
     wayland::{
         compositor::with_states,
-        shell::xdg::{
-            Configure, ToplevelSurface, XdgPopupSurfaceData, XdgShellHandler,
-            XdgToplevelSurfaceData,
-        },
+        shell::xdg::{Configure, ToplevelSurface, XdgPopupSurfaceData, XdgShellHandler},
     },
 };
 
 use crate::{
-    grab::move_grab::MoveSurfaceGrab,
+    grab::{
+        move_grab::MoveSurfaceGrab,
+        resize_grab::{handle_resize_ack, ResizeEdge, ResizeSurfaceGrab},
+    },
+    ipc::protocol::Event,
     shell::WindowElement,
-    state::{Backend, PeddingResize, WindowRemoved, WmStatus},
+    state::Backend,
     FlyJa,
 };
 
@@ -62,36 +64,22 @@ impl<BackendData: Backend> XdgShellHandler for FlyJa<BackendData> {
     }
 
     fn new_toplevel(&mut self, surface: ToplevelSurface) {
-        let window = WindowElement::new(surface.clone());
+        let window = WindowElement::new(surface.clone(), &self.config.border);
+        let focused = self.focused_window();
+        self.workspace_mut()
+            .insert(window.clone(), focused.as_ref());
+        // placed at the initial commit, once the rules are known
         self.space.map_element(window.clone(), (0, 0), true);
-        self.reseize_state = PeddingResize::Resizing(surface.wl_surface().clone());
+        if let Some(window) = self.window_info(&window) {
+            self.ipc_event(Event::WindowOpened { window });
+        }
     }
 
     fn toplevel_destroyed(&mut self, surface: ToplevelSurface) {
-        let Some(window) = self
-            .space
-            .elements()
-            .find(|w| w.toplevel().wl_surface() == surface.wl_surface())
-        else {
+        let Some(window) = self.any_window_for_surface(surface.wl_surface()) else {
             return;
         };
-        let Some(Point { x, y, .. }) = self.space.element_location(window) else {
-            return;
-        };
-        let (x, y, newx, newy) = {
-            let Size {
-                w: width,
-                h: height,
-                ..
-            } = window.geometry().size;
-            (x, y, x + width, y + height)
-        };
-
-        self.window_remove_state = WindowRemoved::Region {
-            pos_start: (x, y),
-            pos_end: (newx, newy),
-        };
-        self.handle_window_removed_mul();
+        self.handle_window_removed(&window);
     }
 
     fn xdg_shell_state(&mut self) -> &mut smithay::wayland::shell::xdg::XdgShellState {
@@ -100,17 +88,40 @@ impl<BackendData: Backend> XdgShellHandler for FlyJa<BackendData> {
 
     fn resize_request(
         &mut self,
-        _surface: ToplevelSurface,
-        _seat: wl_seat::WlSeat,
-        _serial: Serial,
-        _edges: xdg_toplevel::ResizeEdge,
+        surface: ToplevelSurface,
+        seat: wl_seat::WlSeat,
+        serial: Serial,
+        edges: xdg_toplevel::ResizeEdge,
     ) {
+        let seat: Seat<FlyJa<BackendData>> = Seat::from_resource(&seat).unwrap();
+        let wl_surface = surface.wl_surface();
+        let Some(start_data) = check_grab(&seat, wl_surface, serial) else {
+            return;
+        };
+        let Some(window) = self
+            .window_for_surface(wl_surface)
+            .filter(|window| !window.is_fullscreen() && !window.is_maximized())
+        else {
+            return;
+        };
+        let Some(initial_window_location) = self.space.element_location(&window) else {
+            return;
+        };
+        let pointer = seat.get_pointer().unwrap();
+        let tiled = self.is_tiled_window(&window);
+
+        let grab = ResizeSurfaceGrab::new(
+            start_data,
+            window,
+            edges.into(),
+            initial_window_location,
+            tiled,
+        );
+
+        pointer.set_grab(self, grab, serial, Focus::Clear);
     }
 
     fn move_request(&mut self, surface: ToplevelSurface, seat: wl_seat::WlSeat, serial: Serial) {
-        if self.wmstatus == WmStatus::Tile {
-            return;
-        }
         let seat: Seat<FlyJa<BackendData>> = Seat::from_resource(&seat).unwrap();
         let wl_surface = surface.wl_surface();
         let Some(start_data) = check_grab(&seat, wl_surface, serial) else {
@@ -123,6 +134,9 @@ impl<BackendData: Backend> XdgShellHandler for FlyJa<BackendData> {
             .find(|w| w.toplevel().wl_surface() == wl_surface)
             .unwrap()
             .clone();
+        if window.is_fullscreen() || window.is_maximized() || self.is_tiled_window(&window) {
+            return;
+        }
 
         let initial_window_location = self.space.element_location(&window).unwrap();
 
@@ -130,12 +144,61 @@ impl<BackendData: Backend> XdgShellHandler for FlyJa<BackendData> {
             start_data,
             window,
             initial_window_location,
+            tiled: false,
         };
 
         pointer.set_grab(self, grab, serial, Focus::Clear);
     }
 
-    fn ack_configure(&mut self, _surface: wl_surface::WlSurface, _configure: Configure) {}
+    fn fullscreen_request(
+        &mut self,
+        surface: ToplevelSurface,
+        output: Option<wl_output::WlOutput>,
+    ) {
+        let Some(window) = self.any_window_for_surface(surface.wl_surface()) else {
+            return;
+        };
+        let output = output
+            .as_ref()
+            .and_then(Output::from_resource)
+            .or_else(|| self.space.outputs_for_element(&window).first().cloned())
+            .or_else(|| self.space.outputs().next().cloned());
+        if let Some(output) = output {
+            self.set_window_fullscreen(&window, Some(output));
+        }
+    }
+
+    fn unfullscreen_request(&mut self, surface: ToplevelSurface) {
+        if let Some(window) = self.any_window_for_surface(surface.wl_surface()) {
+            self.set_window_fullscreen(&window, None);
+        }
+    }
+
+    fn maximize_request(&mut self, surface: ToplevelSurface) {
+        if let Some(window) = self.any_window_for_surface(surface.wl_surface()) {
+            self.set_window_maximized(&window, true);
+        }
+    }
+
+    fn unmaximize_request(&mut self, surface: ToplevelSurface) {
+        if let Some(window) = self.any_window_for_surface(surface.wl_surface()) {
+            self.set_window_maximized(&window, false);
+        }
+    }
+
+    fn ack_configure(&mut self, surface: wl_surface::WlSurface, configure: Configure) {
+        if let Configure::Toplevel(configure) = configure {
+            handle_resize_ack(&surface, configure.serial);
+        }
+    }
+
+    fn app_id_changed(&mut self, surface: ToplevelSurface) {
+        self.reapply_window_rules(&surface);
+    }
+
+    fn title_changed(&mut self, surface: ToplevelSurface) {
+        self.reapply_window_rules(&surface);
+    }
 }
 
 impl<BackendData: Backend + 'static> FlyJa<BackendData> {
@@ -158,28 +221,194 @@ impl<BackendData: Backend + 'static> FlyJa<BackendData> {
             }
         };
     }
-    pub fn handle_window_commit(&mut self, surface: &wl_surface::WlSurface) -> Option<()> {
-        let window = self
+    /// Cover the output with the window, or put it back where it was with
+    /// None
+    pub fn set_window_fullscreen(&mut self, window: &WindowElement, output: Option<Output>) {
+        if output.is_some() {
+            self.remember_geometry(window);
+        }
+        window.set_fullscreen(output);
+        self.update_placement(window);
+    }
+
+    /// Fill the usable area of the output with the window, or put it back
+    /// where it was
+    pub fn set_window_maximized(&mut self, window: &WindowElement, maximized: bool) {
+        if maximized {
+            self.remember_geometry(window);
+        }
+        window.set_maximized(maximized);
+        self.update_placement(window);
+    }
+
+    pub fn toggle_fullscreen(&mut self, window: &WindowElement) {
+        let output = if window.is_fullscreen() {
+            None
+        } else {
+            self.space
+                .outputs_for_element(window)
+                .first()
+                .cloned()
+                .or_else(|| self.space.outputs().next().cloned())
+        };
+        self.set_window_fullscreen(window, output);
+    }
+
+    pub fn toggle_maximized(&mut self, window: &WindowElement) {
+        self.set_window_maximized(window, !window.is_maximized());
+    }
+
+    /// Take the window out of the tiles to float centered above them, or
+    /// put it back in the tiles
+    pub fn set_window_floating(&mut self, window: &WindowElement, floating: bool) {
+        if window.is_floating() == floating {
+            return;
+        }
+        let Some(index) = self.workspace_of(window) else {
+            return;
+        };
+        self.workspaces[index].set_floating(window, floating);
+        if floating {
+            window.set_tiled_edges(ResizeEdge::empty());
+            if window.initial_configure_sent() {
+                window.toplevel().send_pending_configure();
+            } else {
+                // the client picks its size
+                window.toplevel().with_pending_state(|state| {
+                    state.size = None;
+                });
+            }
+            self.center_window(window);
+        }
+        self.apply_layout();
+    }
+
+    pub fn toggle_floating(&mut self, window: &WindowElement) {
+        self.set_window_floating(window, !window.is_floating());
+    }
+
+    /// Center a floating or stacked window in the usable area of the output
+    /// under the pointer once its size is known
+    fn center_window(&mut self, window: &WindowElement) {
+        if !window.has_contents() || !self.space.elements().any(|w| w == window) {
+            return;
+        }
+        let Some(area) = self.pointer_area() else {
+            return;
+        };
+        if !window.take_center()
+            || self.is_tiled_window(window)
+            || window.is_fullscreen()
+            || window.is_maximized()
+        {
+            return;
+        }
+        let size = window.geometry().size;
+        let location = (
+            area.loc.x + (area.size.w - size.w) / 2,
+            area.loc.y + (area.size.h - size.h) / 2,
+        );
+        self.space.map_element(window.clone(), location, false);
+    }
+
+    /// The geometry the window takes while it is fullscreen or maximized
+    pub fn placement_geometry(&self, window: &WindowElement) -> Option<Rectangle<i32, Logical>> {
+        if let Some(output) = window.fullscreen_output() {
+            return self.space.output_geometry(&output);
+        }
+        if !window.is_maximized() {
+            return None;
+        }
+        let output = self
             .space
-            .elements()
-            .find(|w| w.toplevel().wl_surface() == surface)
-            .cloned()?;
-        let initial_configure_sent = with_states(surface, |states| {
-            states
-                .data_map
-                .get::<XdgToplevelSurfaceData>()
-                .unwrap()
-                .lock()
-                .unwrap()
-                .initial_configure_sent
-        });
+            .outputs_for_element(window)
+            .first()
+            .cloned()
+            .or_else(|| self.space.outputs().next().cloned())?;
+        self.usable_area(&output)
+    }
 
-        if !initial_configure_sent {
-            window.toplevel().send_configure();
-            if let WmStatus::Stack = self.wmstatus {
-                self.reseize_state = PeddingResize::ResizeFinished(surface.clone());
+    /// Keep the geometry of a window which is neither fullscreen nor
+    /// maximized, it is given back when the window leaves both
+    fn remember_geometry(&self, window: &WindowElement) {
+        if window.is_fullscreen() || window.is_maximized() {
+            return;
+        }
+        if let Some(location) = self.space.element_location(window) {
+            window.set_restore_geometry(Rectangle::from_loc_and_size(
+                location,
+                window.geometry().size,
+            ));
+        }
+    }
+
+    /// Place the window after its fullscreen or maximized state changed, a
+    /// tiled window goes back to its tile, a floating one to the geometry
+    /// it had before
+    fn update_placement(&mut self, window: &WindowElement) {
+        if !self.space.elements().any(|w| w == window) {
+            // placed when its workspace is shown again
+            if window.initial_configure_sent() {
+                window.toplevel().send_pending_configure();
             }
+            return;
+        }
+        if let Some(geometry) = self.placement_geometry(window) {
+            self.space.raise_element(window, false);
+            self.place_window(window.clone(), geometry);
+            return;
+        }
+        let restore = window.take_restore_geometry();
+        let tiled = self.workspace().wmstatus.is_tiled()
+            && self.workspace().layout.windows().contains(window);
+        if tiled {
+            self.apply_layout();
+        } else if let Some(geometry) = restore {
+            self.place_window(window.clone(), geometry);
+        } else {
+            // the client picks its size again
+            window.toplevel().with_pending_state(|state| {
+                state.size = None;
+            });
+            if window.initial_configure_sent() {
+                window.toplevel().send_pending_configure();
+            }
+        }
+    }
+
+    /// The rules are applied at the initial commit, then again when the
+    /// app_id or the title of the window changes
+    fn reapply_window_rules(&mut self, surface: &ToplevelSurface) {
+        let Some(window) = self.any_window_for_surface(surface.wl_surface()) else {
+            return;
+        };
+        if window.initial_configure_sent() {
+            self.apply_window_rules(&window);
+        }
+    }
+
+    pub fn handle_window_commit(&mut self, surface: &wl_surface::WlSurface) -> Option<()> {
+        let window = self.any_window_for_surface(surface)?;
+        if !window.initial_configure_sent() {
+            if window.wants_floating() {
+                self.set_window_floating(&window, true);
+            }
+            self.apply_window_rules(&window);
+            if self.is_tiled_window(&window) {
+                // the first buffer already has the size of the tile, a rule
+                // may have moved the window to a workspace not laid out
+                self.apply_layout();
+                if let Some(geometry) = self.tile_geometry(&window) {
+                    window.toplevel().with_pending_state(|state| {
+                        state.size = Some(window.content_size(geometry.size));
+                    });
+                }
+            } else {
+                window.center_when_sized();
+            }
+            window.toplevel().send_configure();
         }
+        self.center_window(&window);
 
         Some(())
     }
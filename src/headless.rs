@@ -0,0 +1,102 @@
+//! The backend of the tests: virtual outputs without a display
+
+use crate::state::Backend;
+use crate::CalloopData;
+use crate::FlyJa;
+use smithay::{
+    output::{Mode, Output, PhysicalProperties, Subpixel},
+    reexports::calloop::{
+        timer::{TimeoutAction, Timer},
+        EventLoop,
+    },
+    utils::{Physical, Size, Transform},
+};
+use std::time::Duration;
+
+pub const OUTPUT_NAME: &str = "headless";
+
+pub struct HeadlessData;
+
+impl Backend for HeadlessData {
+    fn seat_name(&self) -> String {
+        "Headless".to_string()
+    }
+}
+
+/// Create the virtual outputs side by side and drive the frame callbacks
+/// of the clients on a timer. Nothing is rendered, the outputs only exist
+/// for the layout and the clients
+pub fn init_headless<T>(
+    event_loop: &mut EventLoop<CalloopData<T>>,
+    data: &mut CalloopData<T>,
+    sizes: &[Size<i32, Physical>],
+) -> Result<(), Box<dyn std::error::Error>>
+where
+    T: Backend + 'static,
+{
+    let display = &mut data.display;
+    let state = &mut data.state;
+
+    let mut outputs = Vec::new();
+    let mut x = 0;
+    for (index, size) in sizes.iter().enumerate() {
+        let mode = Mode {
+            size: *size,
+            refresh: 60_000,
+        };
+
+        let output = Output::new(
+            format!("{OUTPUT_NAME}-{}", index + 1),
+            PhysicalProperties {
+                size: (0, 0).into(),
+                subpixel: Subpixel::Unknown,
+                make: "Flyja".into(),
+                model: "Headless".into(),
+            },
+        );
+
+        let _global = output.create_global::<FlyJa<T>>(&display.handle());
+        output.change_current_state(
+            Some(mode),
+            Some(Transform::Normal),
+            None,
+            Some((x, 0).into()),
+        );
+        output.set_preferred(mode);
+
+        state.space.map_output(&output, (x, 0));
+        x += size.w;
+        outputs.push(output);
+    }
+
+    let timer = Timer::immediate();
+    event_loop
+        .handle()
+        .insert_source(timer, move |_, _, data| {
+            headless_dispatch(data, &outputs).unwrap();
+            TimeoutAction::ToDuration(Duration::from_millis(16))
+        })?;
+
+    Ok(())
+}
+
+fn headless_dispatch<T>(
+    data: &mut CalloopData<T>,
+    outputs: &[Output],
+) -> Result<(), Box<dyn std::error::Error>>
+where
+    T: Backend + 'static,
+{
+    let display = &mut data.display;
+    let state = &mut data.state;
+
+    for output in outputs {
+        state.send_frames(output);
+    }
+
+    state.space.refresh();
+    state.popups.cleanup();
+    display.flush_clients()?;
+
+    Ok(())
+}
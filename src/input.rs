@@ -1,25 +1,41 @@
 use smithay::{
     backend::input::{
         AbsolutePositionEvent, Axis, AxisSource, ButtonState, Event, InputBackend, InputEvent,
-        KeyState, KeyboardKeyEvent, PointerAxisEvent, PointerButtonEvent,
+        KeyState, KeyboardKeyEvent, PointerAxisEvent, PointerButtonEvent, PointerMotionEvent,
     },
+    desktop::space::SpaceElement,
     input::{
         keyboard::{keysyms as xkb, FilterResult, Keysym, ModifiersState},
-        pointer::{AxisFrame, ButtonEvent, MotionEvent},
+        pointer::{AxisFrame, ButtonEvent, Focus, GrabStartData, MotionEvent},
     },
     reexports::wayland_server::{protocol::wl_surface::WlSurface, DisplayHandle},
-    utils::SERIAL_COUNTER,
+    utils::{Logical, Point, Rectangle, Serial, SERIAL_COUNTER},
+    wayland::shell::wlr_layer::Layer,
 };
 
+use serde::Deserialize;
+
 use crate::{
+    focus::Direction,
+    grab::{
+        move_grab::MoveSurfaceGrab,
+        resize_grab::{ResizeEdge, ResizeSurfaceGrab},
+    },
+    layout::ContainerMode,
+    shell::WindowElement,
     state::{Backend, SplitState},
+    workspace::MASTER_RATIO_STEP,
     FlyJa,
 };
 
+const BTN_LEFT: u32 = 0x110;
+const BTN_RIGHT: u32 = 0x111;
+
 /// Possible results of a keyboard action
 #[allow(dead_code)]
-#[derive(Debug)]
-enum KeyAction {
+#[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "String")]
+pub enum KeyAction {
     /// Quit the compositor
     Quit,
     /// Trigger a vt-switch
@@ -30,11 +46,41 @@ enum KeyAction {
     ChangeSplitSate(SplitState),
     /// Switch the current screen
     Screen(usize),
+    /// Move the focused window to the screen
+    MoveToScreen(usize),
+    /// Focus the closest window in the direction
+    Focus(Direction),
+    /// Exchange the focused tile with its neighbour in the direction
+    Swap(Direction),
+    /// Move the focused tile into the container of its neighbour
+    Move(Direction),
+    /// Put the focused tile at the master position
+    Promote,
+    /// Show the container of the focused tile split, tabbed or stacked
+    Container(ContainerMode),
+    /// Grow or shrink the gap between the tiles by the pixels
+    InnerGaps(i32),
+    /// Grow or shrink the gap around the tiles by the pixels
+    OuterGaps(i32),
+    /// Make the focused window cover its output or leave the fullscreen
+    ToggleFullscreen,
+    /// Make the focused window fill its output or give its size back
+    ToggleMaximize,
+    /// Float the focused window above the tiles or put it back in them
+    ToggleFloating,
+    /// Go to the next layout of the workspace
+    CycleLayout,
+    GrowMaster,
+    ShrinkMaster,
+    MoreMasters,
+    FewerMasters,
     ScaleUp,
     ScaleDown,
     TogglePreview,
     RotateOutput,
     ToggleTint,
+    /// Read the config file again
+    ReloadConfig,
     /// Do nothing more
     None,
 }
@@ -48,20 +94,87 @@ impl<BackendData: Backend + 'static> FlyJa<BackendData> {
     ) {
         match event {
             InputEvent::Keyboard { event } => match self.keyboard_key_to_action::<I>(event) {
+                KeyAction::Quit => {
+                    tracing::info!("Quitting");
+                    self.quit();
+                }
+                KeyAction::VtSwitch(vt) => {
+                    tracing::info!(to = vt, "Trying to switch vt");
+                    self.backend_data.change_vt(vt);
+                }
                 KeyAction::Run(cmd) => {
-                    if let Err(e) = std::process::Command::new(&cmd)
-                        .env("WAYLAND_DISPLAY", self.socket_name.clone())
-                        .spawn()
-                    {
-                        tracing::error!(cmd, err = %e, "Failed to start program");
-                    }
+                    self.spawn(&cmd);
                 }
                 KeyAction::ChangeWmState => {
-                    self.wmstatus.status_change();
+                    self.change_wm_status();
+                }
+                KeyAction::CycleLayout => {
+                    self.cycle_wm_status();
+                }
+                KeyAction::GrowMaster => {
+                    self.change_master_ratio(MASTER_RATIO_STEP);
+                }
+                KeyAction::ShrinkMaster => {
+                    self.change_master_ratio(-MASTER_RATIO_STEP);
+                }
+                KeyAction::MoreMasters => {
+                    self.change_master_count(1);
+                }
+                KeyAction::FewerMasters => {
+                    self.change_master_count(-1);
                 }
                 KeyAction::ChangeSplitSate(state) => {
                     self.set_split_state(state);
                 }
+                KeyAction::ReloadConfig => {
+                    self.reload_config();
+                }
+                KeyAction::Screen(index) => {
+                    self.switch_workspace(index);
+                }
+                KeyAction::MoveToScreen(index) => {
+                    if let Some(window) = self.focused_window() {
+                        self.move_window_to_workspace(&window, index);
+                    }
+                }
+                KeyAction::Focus(direction) => {
+                    self.focus_direction(direction);
+                }
+                KeyAction::Swap(direction) => {
+                    self.swap_focused(direction);
+                }
+                KeyAction::Move(direction) => {
+                    self.move_focused(direction);
+                }
+                KeyAction::Promote => {
+                    self.promote_focused();
+                }
+                KeyAction::Container(mode) => {
+                    self.set_focused_container_mode(mode);
+                }
+                KeyAction::InnerGaps(delta) => {
+                    let inner = self.config.layout.gaps.inner + delta;
+                    self.set_gaps(Some(inner), None);
+                }
+                KeyAction::OuterGaps(delta) => {
+                    let outer = self.config.layout.gaps.outer + delta;
+                    self.set_gaps(None, Some(outer));
+                }
+                KeyAction::ToggleFullscreen => {
+                    if let Some(window) = self.focused_window() {
+                        self.toggle_fullscreen(&window);
+                    }
+                }
+                KeyAction::ToggleMaximize => {
+                    if let Some(window) = self.focused_window() {
+                        self.toggle_maximized(&window);
+                    }
+                }
+                KeyAction::ToggleFloating => {
+                    if let Some(window) = self.focused_window() {
+                        self.toggle_floating(&window);
+                    }
+                }
                 _ => {}
             },
             // Mouse or touch pad
@@ -75,26 +188,34 @@ impl<BackendData: Backend + 'static> FlyJa<BackendData> {
 
                 let button_state = event.state();
 
-                if ButtonState::Pressed == button_state && !pointer.is_grabbed() {
-                    if let Some((window, _loc)) = self
-                        .space
-                        .element_under(pointer.current_location())
-                        .map(|(w, l)| (w.clone(), l))
+                // an exclusive layer surface keeps the keyboard until it is gone
+                if ButtonState::Pressed == button_state
+                    && !pointer.is_grabbed()
+                    && self.exclusive_layer().is_none()
+                {
+                    let pos = pointer.current_location();
+                    let fullscreen = self.fullscreen_window_under(pos);
+                    if let Some(layer) = self
+                        .layer_under(&[Layer::Overlay, Layer::Top], pos)
+                        .filter(|_| fullscreen.is_none())
+                    {
+                        if layer.can_receive_keyboard_focus() {
+                            keyboard.set_focus(self, Some(layer.wl_surface().clone()), serial);
+                        }
+                    } else if let Some(window) = fullscreen
+                        .or_else(|| self.space.element_under(pos).map(|(w, _)| w.clone()))
+                        .or_else(|| self.tab_under(pos))
                     {
-                        self.space.raise_element(&window, true);
-                        keyboard.set_focus(
-                            self,
-                            Some(window.toplevel().wl_surface().clone()),
-                            serial,
-                        );
-                        self.space.elements().for_each(|window| {
-                            window.toplevel().send_configure();
-                        });
+                        self.focus_window(&window, serial);
+                        if keyboard.modifier_state().logo {
+                            self.start_modifier_grab(window, button, serial);
+                        }
+                    } else if let Some(layer) = self
+                        .layer_under(&[Layer::Bottom, Layer::Background], pos)
+                        .filter(|layer| layer.can_receive_keyboard_focus())
+                    {
+                        keyboard.set_focus(self, Some(layer.wl_surface().clone()), serial);
                     } else {
-                        self.space.elements().for_each(|window| {
-                            window.set_activated(false);
-                            window.toplevel().send_configure();
-                        });
                         keyboard.set_focus(self, Option::<WlSurface>::None, serial);
                     }
                 }
@@ -108,6 +229,27 @@ impl<BackendData: Backend + 'static> FlyJa<BackendData> {
                     },
                 );
             }
+            InputEvent::PointerMotion { event } => {
+                let pointer = self.seat.get_pointer().unwrap();
+                let pos = self.clamp_to_outputs(
+                    pointer.current_location(),
+                    pointer.current_location() + event.delta(),
+                );
+                let serial = SERIAL_COUNTER.next_serial();
+
+                let under = self.surface_under(pos);
+
+                pointer.motion(
+                    self,
+                    under,
+                    &MotionEvent {
+                        location: pos,
+                        serial,
+                        time: event.time_msec(),
+                    },
+                );
+                self.focus_under_pointer(pos, serial);
+            }
             InputEvent::PointerMotionAbsolute { event } => {
                 let output = self.space.outputs().next().unwrap();
 
@@ -118,7 +260,7 @@ impl<BackendData: Backend + 'static> FlyJa<BackendData> {
 
                 let pointer = self.seat.get_pointer().unwrap();
 
-                let under = self.surface_under_pointer(&pointer);
+                let under = self.surface_under(pos);
 
                 pointer.motion(
                     self,
@@ -129,6 +271,7 @@ impl<BackendData: Backend + 'static> FlyJa<BackendData> {
                         time: event.time_msec(),
                     },
                 );
+                self.focus_under_pointer(pos, serial);
             }
             // scroll
             InputEvent::PointerAxis { event, .. } => {
@@ -171,6 +314,91 @@ impl<BackendData: Backend + 'static> FlyJa<BackendData> {
 }
 
 impl<BackendData: Backend + 'static> FlyJa<BackendData> {
+    /// Logo+left drag moves the window, Logo+right drag resizes it from the
+    /// corner closest to the pointer
+    fn start_modifier_grab(&mut self, window: WindowElement, button: u32, serial: Serial) {
+        if window.is_fullscreen() || window.is_maximized() {
+            return;
+        }
+        let pointer = self.seat.get_pointer().unwrap();
+        let Some(initial_window_location) = self.space.element_location(&window) else {
+            return;
+        };
+        let location = pointer.current_location();
+        let start_data = GrabStartData {
+            focus: None,
+            button,
+            location,
+        };
+        let tiled = self.is_tiled_window(&window);
+        match button {
+            BTN_LEFT => {
+                let grab = MoveSurfaceGrab {
+                    start_data,
+                    window,
+                    initial_window_location,
+                    tiled,
+                };
+                pointer.set_grab(self, grab, serial, Focus::Clear);
+            }
+            BTN_RIGHT => {
+                let size = window.geometry().size;
+                let relative = location - initial_window_location.to_f64();
+                let horizontal = if relative.x < size.w as f64 / 2.0 {
+                    ResizeEdge::LEFT
+                } else {
+                    ResizeEdge::RIGHT
+                };
+                let vertical = if relative.y < size.h as f64 / 2.0 {
+                    ResizeEdge::TOP
+                } else {
+                    ResizeEdge::BOTTOM
+                };
+                let grab = ResizeSurfaceGrab::new(
+                    start_data,
+                    window,
+                    horizontal | vertical,
+                    initial_window_location,
+                    tiled,
+                );
+                pointer.set_grab(self, grab, serial, Focus::Clear);
+            }
+            _ => {}
+        }
+    }
+
+    /// Keep the pointer on the outputs, when it leaves them it stays on
+    /// the edge of the output it was on
+    fn clamp_to_outputs(
+        &self,
+        current: Point<f64, Logical>,
+        pos: Point<f64, Logical>,
+    ) -> Point<f64, Logical> {
+        let geometries: Vec<Rectangle<f64, Logical>> = self
+            .space
+            .outputs()
+            .filter_map(|output| self.space.output_geometry(output))
+            .map(|geometry| geometry.to_f64())
+            .collect();
+        if geometries.iter().any(|geometry| geometry.contains(pos)) {
+            return pos;
+        }
+        let Some(geometry) = geometries
+            .iter()
+            .find(|geometry| geometry.contains(current))
+            .or_else(|| geometries.first())
+        else {
+            return pos;
+        };
+        (
+            pos.x
+                .clamp(geometry.loc.x, geometry.loc.x + geometry.size.w - 1.0),
+            pos.y
+                .clamp(geometry.loc.y, geometry.loc.y + geometry.size.h - 1.0),
+        )
+            .into()
+    }
+
     fn keyboard_key_to_action<B: InputBackend>(&mut self, evt: B::KeyboardKeyEvent) -> KeyAction {
         let keycode = evt.key_code();
         let state = evt.state();
@@ -185,10 +413,11 @@ impl<BackendData: Backend + 'static> FlyJa<BackendData> {
                 state,
                 serial,
                 time,
-                |_, modifiers, handle| {
+                |data, modifiers, handle| {
                     let keysym = handle.modified_sym();
+                    let raw_keysym = handle.raw_syms().first().copied().unwrap_or(keysym);
                     if let KeyState::Pressed = state {
-                        let action = process_keyboard_shortcut(*modifiers, keysym);
+                        let action = process_keyboard_shortcut(data, modifiers, keysym, raw_keysym);
                         action
                             .map(FilterResult::Intercept)
                             .unwrap_or(FilterResult::Forward)
@@ -200,40 +429,18 @@ impl<BackendData: Backend + 'static> FlyJa<BackendData> {
             .unwrap_or(KeyAction::None)
     }
 }
-fn process_keyboard_shortcut(modifiers: ModifiersState, keysym: Keysym) -> Option<KeyAction> {
-    if modifiers.ctrl && modifiers.alt && keysym == xkb::KEY_BackSpace
-        || modifiers.logo && keysym == xkb::KEY_q
-    {
-        // ctrl+alt+backspace = quit
-        // logo + q = quit
-        Some(KeyAction::Quit)
-    } else if (xkb::KEY_XF86Switch_VT_1..=xkb::KEY_XF86Switch_VT_12).contains(&keysym) {
+fn process_keyboard_shortcut<BackendData: Backend>(
+    data: &FlyJa<BackendData>,
+    modifiers: &ModifiersState,
+    keysym: Keysym,
+    raw_keysym: Keysym,
+) -> Option<KeyAction> {
+    if (xkb::KEY_XF86Switch_VT_1..=xkb::KEY_XF86Switch_VT_12).contains(&keysym) {
         // VTSwitch
         Some(KeyAction::VtSwitch(
             (keysym - xkb::KEY_XF86Switch_VT_1 + 1) as i32,
         ))
-    } else if modifiers.logo && keysym == xkb::KEY_Return {
-        // run terminal
-        Some(KeyAction::Run("wezterm".into()))
-    } else if modifiers.logo && (xkb::KEY_1..=xkb::KEY_9).contains(&keysym) {
-        Some(KeyAction::Screen((keysym - xkb::KEY_1) as usize))
-    } else if modifiers.logo && modifiers.shift && keysym == xkb::KEY_M {
-        Some(KeyAction::ScaleDown)
-    } else if modifiers.logo && keysym == xkb::KEY_P {
-        Some(KeyAction::ChangeWmState)
-    } else if modifiers.logo && modifiers.shift && keysym == xkb::KEY_P {
-        Some(KeyAction::ScaleUp)
-    } else if modifiers.logo && modifiers.shift && keysym == xkb::KEY_W {
-        Some(KeyAction::TogglePreview)
-    } else if modifiers.logo && modifiers.shift && keysym == xkb::KEY_R {
-        Some(KeyAction::RotateOutput)
-    } else if modifiers.logo && modifiers.shift && keysym == xkb::KEY_T {
-        Some(KeyAction::ToggleTint)
-    } else if modifiers.logo && keysym == xkb::KEY_v {
-        Some(KeyAction::ChangeSplitSate(SplitState::V))
-    } else if modifiers.logo && keysym == xkb::KEY_b {
-        Some(KeyAction::ChangeSplitSate(SplitState::H))
     } else {
-        None
+        data.config.binding(modifiers, keysym, raw_keysym)
     }
 }
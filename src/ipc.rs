@@ -0,0 +1,440 @@
+pub mod protocol;
+
+use std::{
+    cell::RefCell,
+    ffi::OsStr,
+    io::{ErrorKind, Read, Write},
+    os::unix::net::{UnixListener, UnixStream},
+    path::PathBuf,
+    rc::Rc,
+};
+
+use serde::Serialize;
+use smithay::{
+    desktop::space::SpaceElement,
+    reexports::calloop::{generic::Generic, Interest, LoopHandle, Mode, PostAction},
+    utils::SERIAL_COUNTER,
+};
+
+use crate::{
+    shell::WindowElement,
+    state::{Backend, SplitState, WmStatus},
+    CalloopData, FlyJa,
+};
+
+use protocol::{
+    Event, EventKind, Layout, OutputInfo, Reply, Request, Response, RuleInfo, Split, WindowInfo,
+    WorkspaceInfo,
+};
+
+impl From<WmStatus> for Layout {
+    fn from(value: WmStatus) -> Self {
+        match value {
+            WmStatus::Tile => Layout::Tile,
+            WmStatus::Master => Layout::Master,
+            WmStatus::Scroll => Layout::Scroll,
+            WmStatus::Stack => Layout::Stack,
+        }
+    }
+}
+
+impl From<Layout> for WmStatus {
+    fn from(value: Layout) -> Self {
+        match value {
+            Layout::Tile => WmStatus::Tile,
+            Layout::Master => WmStatus::Master,
+            Layout::Scroll => WmStatus::Scroll,
+            Layout::Stack => WmStatus::Stack,
+        }
+    }
+}
+
+impl From<Split> for SplitState {
+    fn from(value: Split) -> Self {
+        match value {
+            Split::H => SplitState::H,
+            Split::V => SplitState::V,
+        }
+    }
+}
+
+/// Longest request line a client can send, it is disconnected past it
+const MAX_REQUEST_LEN: usize = 64 * 1024;
+/// Most bytes queued for a client not reading them, it is disconnected
+/// past it
+const MAX_OUTGOING_LEN: usize = 1024 * 1024;
+
+/// The writing side of a client, what the socket does not take at once
+/// is queued and written once it is writable again
+#[derive(Debug)]
+struct IpcClient {
+    stream: UnixStream,
+    outgoing: Vec<u8>,
+    /// the client hung up
+    closed: bool,
+}
+
+impl IpcClient {
+    /// Queue the message as a line and write what the socket takes
+    fn send<T: Serialize>(&mut self, message: &T) -> std::io::Result<()> {
+        if self.closed {
+            return Err(ErrorKind::BrokenPipe.into());
+        }
+        serde_json::to_writer(&mut self.outgoing, message)?;
+        self.outgoing.push(b'\n');
+        self.flush()?;
+        if self.outgoing.len() > MAX_OUTGOING_LEN {
+            return Err(std::io::Error::new(
+                ErrorKind::Other,
+                "too many bytes queued",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Forget the client, its source is removed from the loop
+    fn hang_up(&mut self) -> PostAction {
+        self.closed = true;
+        PostAction::Remove
+    }
+
+    /// Write the queued bytes until the socket would block
+    fn flush(&mut self) -> std::io::Result<()> {
+        while !self.outgoing.is_empty() {
+            match self.stream.write(&self.outgoing) {
+                Ok(0) => return Err(ErrorKind::WriteZero.into()),
+                Ok(len) => {
+                    self.outgoing.drain(..len);
+                }
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(err) if err.kind() == ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A client which asked for events
+#[derive(Debug)]
+struct Subscriber {
+    client: Rc<RefCell<IpcClient>>,
+    events: Vec<EventKind>,
+}
+
+#[derive(Debug)]
+pub struct IpcState {
+    pub socket_path: Option<PathBuf>,
+    subscribers: Vec<Subscriber>,
+}
+
+impl IpcState {
+    pub fn new(socket_path: Option<PathBuf>) -> Self {
+        Self {
+            socket_path,
+            subscribers: Vec::new(),
+        }
+    }
+}
+
+impl Drop for IpcState {
+    fn drop(&mut self) {
+        if let Some(path) = &self.socket_path {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+/// Listen on `$XDG_RUNTIME_DIR/flyja-<wayland socket>.sock`, the programs
+/// flyja spawns find the path in `FLYJA_SOCK`
+pub fn init_ipc_listener<BackendData: Backend + 'static>(
+    handle: &LoopHandle<'_, CalloopData<BackendData>>,
+    socket_name: &OsStr,
+) -> Option<PathBuf> {
+    let runtime_dir = std::env::var_os("XDG_RUNTIME_DIR").map(PathBuf::from)?;
+    let path = runtime_dir.join(format!("flyja-{}.sock", socket_name.to_string_lossy()));
+    let _ = std::fs::remove_file(&path);
+    let listener = match UnixListener::bind(&path) {
+        Ok(listener) => listener,
+        Err(err) => {
+            tracing::error!("Cannot listen on {}: {}", path.display(), err);
+            return None;
+        }
+    };
+    listener.set_nonblocking(true).ok()?;
+
+    let loop_handle = handle.clone();
+    handle
+        .insert_source(
+            Generic::new(listener, Interest::READ, Mode::Level),
+            move |_, listener, _| {
+                loop {
+                    match listener.accept() {
+                        Ok((stream, _)) => insert_ipc_client(&loop_handle, stream),
+                        Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                        Err(err) => return Err(err),
+                    }
+                }
+                Ok(PostAction::Continue)
+            },
+        )
+        .expect("Failed to init the ipc socket");
+
+    tracing::info!("Listening for ipc on {}", path.display());
+    Some(path)
+}
+
+fn insert_ipc_client<BackendData: Backend + 'static>(
+    handle: &LoopHandle<'_, CalloopData<BackendData>>,
+    stream: UnixStream,
+) {
+    let writer = match stream
+        .set_nonblocking(true)
+        .and_then(|_| stream.try_clone())
+    {
+        Ok(writer) => writer,
+        Err(err) => {
+            tracing::warn!("Failed to set up ipc client: {}", err);
+            return;
+        }
+    };
+    let client = Rc::new(RefCell::new(IpcClient {
+        stream: writer,
+        outgoing: Vec::new(),
+        closed: false,
+    }));
+    let mut buffer = Vec::new();
+    // edge triggered, the socket is read until it would block and the
+    // queued bytes are written each time it becomes writable again
+    let inserted = handle.insert_source(
+        Generic::new(stream, Interest::BOTH, Mode::Edge),
+        move |_, stream, data| {
+            if client.borrow_mut().flush().is_err() {
+                return Ok(client.borrow_mut().hang_up());
+            }
+            let mut chunk = [0; 4096];
+            loop {
+                match stream.read(&mut chunk) {
+                    Ok(0) => return Ok(client.borrow_mut().hang_up()),
+                    Ok(len) => buffer.extend_from_slice(&chunk[..len]),
+                    Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                    Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                    Err(_) => return Ok(client.borrow_mut().hang_up()),
+                }
+                while let Some(end) = buffer.iter().position(|byte| *byte == b'\n') {
+                    let line: Vec<u8> = buffer.drain(..=end).collect();
+                    let response = match serde_json::from_slice::<Request>(&line) {
+                        Ok(request) => data.state.handle_ipc_request(request, &client),
+                        Err(err) => Response::Error(format!("Invalid request: {err}")),
+                    };
+                    if client.borrow_mut().send(&response).is_err() {
+                        return Ok(client.borrow_mut().hang_up());
+                    }
+                }
+                if buffer.len() > MAX_REQUEST_LEN {
+                    tracing::warn!("Dropping an ipc client sending a line too long");
+                    return Ok(client.borrow_mut().hang_up());
+                }
+            }
+            Ok(PostAction::Continue)
+        },
+    );
+    if let Err(err) = inserted {
+        tracing::warn!("Failed to set up ipc client: {}", err);
+    }
+}
+
+impl<BackendData: Backend + 'static> FlyJa<BackendData> {
+    /// Send the event to the clients subscribed to it, the ones which
+    /// cannot be written to are dropped
+    pub fn ipc_event(&mut self, event: Event) {
+        let kind = event.kind();
+        self.ipc_state.subscribers.retain_mut(|subscriber| {
+            !subscriber.events.contains(&kind)
+                || subscriber.client.borrow_mut().send(&event).is_ok()
+        });
+    }
+
+    pub fn window_info(&self, window: &WindowElement) -> Option<WindowInfo> {
+        let workspace = self.workspace_of(window)?;
+        let location = match self.space.element_location(window) {
+            Some(location) => location,
+            None => self.workspaces[workspace].hidden_location(window),
+        };
+        let size = window.geometry().size;
+        let focused = self.focused_window().as_ref() == Some(window);
+        Some(WindowInfo {
+            id: window.id(),
+            app_id: window.app_id(),
+            title: window.title(),
+            workspace: workspace + 1,
+            x: location.x,
+            y: location.y,
+            width: size.w,
+            height: size.h,
+            focused,
+        })
+    }
+
+    fn all_windows(&self) -> Vec<WindowElement> {
+        let mut windows: Vec<WindowElement> = self.space.elements().cloned().collect();
+        for window in self
+            .workspaces
+            .iter()
+            .flat_map(|workspace| workspace.windows())
+        {
+            if !windows.contains(&window) {
+                windows.push(window);
+            }
+        }
+        windows
+    }
+
+    fn window_by_id(&self, id: u64) -> Option<WindowElement> {
+        self.all_windows()
+            .into_iter()
+            .find(|window| window.id() == id)
+    }
+
+    fn handle_ipc_request(
+        &mut self,
+        request: Request,
+        client: &Rc<RefCell<IpcClient>>,
+    ) -> Response {
+        let reply = match request {
+            Request::Windows => Reply::Windows {
+                windows: self
+                    .all_windows()
+                    .iter()
+                    .filter_map(|window| self.window_info(window))
+                    .collect(),
+            },
+            Request::Outputs => Reply::Outputs {
+                outputs: self
+                    .space
+                    .outputs()
+                    .filter_map(|output| {
+                        let geometry = self.space.output_geometry(output)?;
+                        let properties = output.physical_properties();
+                        Some(OutputInfo {
+                            name: output.name(),
+                            make: properties.make,
+                            model: properties.model,
+                            x: geometry.loc.x,
+                            y: geometry.loc.y,
+                            width: geometry.size.w,
+                            height: geometry.size.h,
+                            refresh: output.current_mode().map(|mode| mode.refresh).unwrap_or(0),
+                            scale: output.current_scale().fractional_scale(),
+                        })
+                    })
+                    .collect(),
+            },
+            Request::Workspaces => Reply::Workspaces {
+                workspaces: self
+                    .workspaces
+                    .iter()
+                    .enumerate()
+                    .map(|(index, workspace)| WorkspaceInfo {
+                        index: index + 1,
+                        focused: index == self.current_workspace,
+                        layout: workspace.wmstatus.into(),
+                        windows: workspace.windows().len(),
+                    })
+                    .collect(),
+            },
+            Request::Rules => {
+                let windows = self.all_windows();
+                let generation = self.config.generation;
+                Reply::Rules {
+                    rules: self
+                        .config
+                        .rules
+                        .iter()
+                        .enumerate()
+                        .map(|(index, rule)| RuleInfo {
+                            index: index + 1,
+                            app_id: rule.app_id.as_ref().map(|p| p.as_str().to_string()),
+                            title: rule.title.as_ref().map(|p| p.as_str().to_string()),
+                            floating: rule.floating,
+                            workspace: rule.workspace,
+                            size: rule.size,
+                            border: rule.border,
+                            windows: windows
+                                .iter()
+                                .filter(|window| window.rules(generation).contains(&index))
+                                .map(WindowElement::id)
+                                .collect(),
+                        })
+                        .collect(),
+                }
+            }
+            Request::Focus { id } => {
+                let Some(window) = self.window_by_id(id) else {
+                    return Response::Error(format!("No window with id {id}"));
+                };
+                if let Some(workspace) = self.workspace_of(&window) {
+                    self.switch_workspace(workspace);
+                }
+                self.focus_window(&window, SERIAL_COUNTER.next_serial());
+                Reply::Done
+            }
+            Request::Close { id } => {
+                let Some(window) = self.window_by_id(id) else {
+                    return Response::Error(format!("No window with id {id}"));
+                };
+                window.toplevel().send_close();
+                Reply::Done
+            }
+            Request::MoveToWorkspace { id, workspace } => {
+                let Some(window) = self.window_by_id(id) else {
+                    return Response::Error(format!("No window with id {id}"));
+                };
+                let Some(index) = self.workspace_index(workspace) else {
+                    return Response::Error(format!("No workspace {workspace}"));
+                };
+                self.move_window_to_workspace(&window, index);
+                Reply::Done
+            }
+            Request::SwitchWorkspace { workspace } => {
+                let Some(index) = self.workspace_index(workspace) else {
+                    return Response::Error(format!("No workspace {workspace}"));
+                };
+                self.switch_workspace(index);
+                Reply::Done
+            }
+            Request::SetLayout { layout } => {
+                self.workspace_mut().wmstatus = layout.into();
+                self.wm_status_changed();
+                Reply::Done
+            }
+            Request::SetSplit { split } => {
+                self.set_split_state(split.into());
+                Reply::Done
+            }
+            Request::SetGaps { inner, outer } => {
+                self.set_gaps(inner, outer);
+                Reply::Done
+            }
+            Request::Exec { command } => {
+                self.spawn(&command);
+                Reply::Done
+            }
+            Request::Subscribe { events } => {
+                self.ipc_state.subscribers.push(Subscriber {
+                    client: client.clone(),
+                    events,
+                });
+                Reply::Subscribed
+            }
+        };
+        Response::Ok(reply)
+    }
+
+    /// index of the workspace numbered from 1
+    fn workspace_index(&self, workspace: usize) -> Option<usize> {
+        workspace
+            .checked_sub(1)
+            .filter(|index| *index < self.workspaces.len())
+    }
+}
@@ -0,0 +1,158 @@
+//! The messages of the flyja IPC socket. Every request and response is one
+//! line of JSON, the path of the socket is in `FLYJA_SOCK`. Workspaces are
+//! numbered from 1 like in the bindings.
+
+use serde::{Deserialize, Serialize};
+
+pub const SOCKET_ENV: &str = "FLYJA_SOCK";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Layout {
+    Tile,
+    Master,
+    Scroll,
+    Stack,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Split {
+    H,
+    V,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EventKind {
+    Window,
+    Workspace,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "request", rename_all = "snake_case")]
+pub enum Request {
+    Windows,
+    Outputs,
+    Workspaces,
+    Rules,
+    Focus {
+        id: u64,
+    },
+    Close {
+        id: u64,
+    },
+    MoveToWorkspace {
+        id: u64,
+        workspace: usize,
+    },
+    SwitchWorkspace {
+        workspace: usize,
+    },
+    SetLayout {
+        layout: Layout,
+    },
+    SetSplit {
+        split: Split,
+    },
+    /// Set the gaps in logical pixels, the ones not given are kept
+    SetGaps {
+        #[serde(default)]
+        inner: Option<i32>,
+        #[serde(default)]
+        outer: Option<i32>,
+    },
+    Exec {
+        command: String,
+    },
+    /// Keep the connection open and receive the events of these kinds
+    Subscribe {
+        events: Vec<EventKind>,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WindowInfo {
+    pub id: u64,
+    pub app_id: Option<String>,
+    pub title: Option<String>,
+    pub workspace: usize,
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+    pub focused: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutputInfo {
+    pub name: String,
+    pub make: String,
+    pub model: String,
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+    pub refresh: i32,
+    pub scale: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkspaceInfo {
+    pub index: usize,
+    pub focused: bool,
+    pub layout: Layout,
+    pub windows: usize,
+}
+
+/// A window rule of the config, the patterns are the regexes as written
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuleInfo {
+    pub index: usize,
+    pub app_id: Option<String>,
+    pub title: Option<String>,
+    pub floating: Option<bool>,
+    pub workspace: Option<usize>,
+    pub size: Option<(i32, i32)>,
+    pub border: Option<bool>,
+    /// ids of the windows matching the rule
+    pub windows: Vec<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "reply", rename_all = "snake_case")]
+pub enum Reply {
+    Done,
+    Windows { windows: Vec<WindowInfo> },
+    Outputs { outputs: Vec<OutputInfo> },
+    Workspaces { workspaces: Vec<WorkspaceInfo> },
+    Rules { rules: Vec<RuleInfo> },
+    Subscribed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Response {
+    Ok(Reply),
+    Error(String),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    WindowOpened { window: WindowInfo },
+    WindowClosed { id: u64 },
+    WindowFocused { id: u64 },
+    WorkspaceChanged { workspace: usize },
+}
+
+impl Event {
+    pub fn kind(&self) -> EventKind {
+        match self {
+            Event::WindowOpened { .. }
+            | Event::WindowClosed { .. }
+            | Event::WindowFocused { .. } => EventKind::Window,
+            Event::WorkspaceChanged { .. } => EventKind::Workspace,
+        }
+    }
+}
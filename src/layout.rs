@@ -0,0 +1,788 @@
+use smithay::utils::{Logical, Rectangle};
+
+use crate::{
+    focus::Direction,
+    grab::resize_grab::ResizeEdge,
+    shell::WindowElement,
+    state::{Backend, SplitState, WmStatus},
+    FlyJa,
+};
+
+/// The smallest part of a container a child can be resized to
+const MIN_RATIO: f64 = 0.05;
+
+/// The height of a tab or of a title of a stacked container
+pub const TAB_HEIGHT: i32 = 20;
+
+/// How a container shows its children
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ContainerMode {
+    /// side by side or on top of each other, following the split state
+    #[default]
+    Split,
+    /// the selected child under a row of tabs
+    Tabbed,
+    /// the selected child under a column of titles
+    Stacked,
+}
+
+impl TryFrom<&str> for ContainerMode {
+    type Error = String;
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "split" => Ok(ContainerMode::Split),
+            "tabbed" => Ok(ContainerMode::Tabbed),
+            "stacked" => Ok(ContainerMode::Stacked),
+            _ => Err(format!("unknown container mode `{value}`")),
+        }
+    }
+}
+
+/// The tab of a child of a tabbed or stacked container, the window is the
+/// one focused by clicking it and whose title is shown
+#[derive(Debug, Clone)]
+pub struct Tab {
+    pub window: WindowElement,
+    pub geometry: Rectangle<i32, Logical>,
+    pub selected: bool,
+}
+
+/// Where the windows of the tree go when it is laid into an area
+#[derive(Debug, Default)]
+pub struct Arrangement {
+    /// the windows shown
+    pub tiles: Vec<(WindowElement, Rectangle<i32, Logical>)>,
+    /// the windows of the tabs not selected, at the place of the selected one
+    pub hidden: Vec<(WindowElement, Rectangle<i32, Logical>)>,
+    pub tabs: Vec<Tab>,
+    /// the space left between two tiles
+    pub gap: i32,
+}
+
+/// A node of the tile tree, either a window or a container splitting its
+/// area between its children
+#[derive(Debug)]
+pub enum LayoutNode {
+    Leaf(WindowElement),
+    Split(SplitNode),
+}
+
+/// A container laying its children side by side (`SplitState::H`) or on top
+/// of each other (`SplitState::V`), or showing only the selected one in the
+/// tabbed and stacked modes. `ratios` always sums up to 1.0
+#[derive(Debug)]
+pub struct SplitNode {
+    pub state: SplitState,
+    pub mode: ContainerMode,
+    pub children: Vec<LayoutNode>,
+    pub ratios: Vec<f64>,
+    /// the child shown in the tabbed and stacked modes
+    pub selected: usize,
+}
+
+impl SplitNode {
+    fn new(state: SplitState, children: Vec<LayoutNode>) -> Self {
+        let ratio = 1.0 / children.len() as f64;
+        let ratios = vec![ratio; children.len()];
+        Self {
+            state,
+            mode: ContainerMode::Split,
+            children,
+            ratios,
+            selected: 0,
+        }
+    }
+
+    fn position(&self, window: &WindowElement) -> Option<usize> {
+        self.children
+            .iter()
+            .position(|child| matches!(child, LayoutNode::Leaf(leaf) if leaf == window))
+    }
+
+    /// share the area of the child at index with the new window
+    fn split_child(&mut self, index: usize, window: WindowElement) {
+        let half = self.ratios[index] / 2.0;
+        self.ratios[index] = half;
+        self.children.insert(index + 1, LayoutNode::Leaf(window));
+        self.ratios.insert(index + 1, half);
+        if self.mode != ContainerMode::Split {
+            // a new tab is shown
+            self.selected = index + 1;
+        } else if self.selected > index {
+            self.selected += 1;
+        }
+    }
+
+    /// the height of the tabs or titles above the children
+    fn bar_height(&self, area: Rectangle<i32, Logical>) -> i32 {
+        let height = match self.mode {
+            ContainerMode::Split => 0,
+            ContainerMode::Tabbed => TAB_HEIGHT,
+            ContainerMode::Stacked => TAB_HEIGHT * self.children.len() as i32,
+        };
+        height.min(area.size.h)
+    }
+
+    /// the tabs or titles of the children, in the bar above them
+    fn tab_areas(&self, area: Rectangle<i32, Logical>) -> Vec<Rectangle<i32, Logical>> {
+        let count = self.children.len() as i32;
+        (0..count)
+            .map(|index| match self.mode {
+                ContainerMode::Split => Rectangle::default(),
+                ContainerMode::Tabbed => {
+                    let x = area.size.w * index / count;
+                    let w = area.size.w * (index + 1) / count - x;
+                    Rectangle::from_loc_and_size((area.loc.x + x, area.loc.y), (w, TAB_HEIGHT))
+                }
+                ContainerMode::Stacked => Rectangle::from_loc_and_size(
+                    (area.loc.x, area.loc.y + TAB_HEIGHT * index),
+                    (area.size.w, TAB_HEIGHT),
+                ),
+            })
+            .collect()
+    }
+
+    /// the areas of the children when the container is laid into area with
+    /// gap between them, all the children take the area under the bar in the
+    /// tabbed and stacked modes
+    fn child_areas(&self, area: Rectangle<i32, Logical>, gap: i32) -> Vec<Rectangle<i32, Logical>> {
+        if self.mode != ContainerMode::Split {
+            let bar = self.bar_height(area);
+            let content = Rectangle::from_loc_and_size(
+                (area.loc.x, area.loc.y + bar),
+                (area.size.w, area.size.h - bar),
+            );
+            return vec![content; self.children.len()];
+        }
+        let last = self.children.len() - 1;
+        let total = match self.state {
+            SplitState::H => area.size.w,
+            SplitState::V => area.size.h,
+        } - gap * last as i32;
+        let mut offset = 0;
+        let mut areas = Vec::with_capacity(self.children.len());
+        for (index, ratio) in self.ratios.iter().enumerate() {
+            // the last one takes what is left, so rounding never leaves a hole
+            let length = if index == last {
+                total - offset
+            } else {
+                (total as f64 * ratio).round() as i32
+            };
+            let position = offset + gap * index as i32;
+            areas.push(match self.state {
+                SplitState::H => Rectangle::from_loc_and_size(
+                    (area.loc.x + position, area.loc.y),
+                    (length, area.size.h),
+                ),
+                SplitState::V => Rectangle::from_loc_and_size(
+                    (area.loc.x, area.loc.y + position),
+                    (area.size.w, length),
+                ),
+            });
+            offset += length;
+        }
+        areas
+    }
+
+    /// remove the child at index, the area is given to the neighbour before it
+    fn remove_child(&mut self, index: usize) {
+        let ratio = self.ratios.remove(index);
+        self.children.remove(index);
+        if self.selected > index || self.selected >= self.children.len() {
+            self.selected = self.selected.saturating_sub(1);
+        }
+        if self.ratios.is_empty() {
+            return;
+        }
+        let neighbour = index.saturating_sub(1);
+        self.ratios[neighbour] += ratio;
+    }
+}
+
+impl LayoutNode {
+    /// The state of the container the window is a child of
+    fn parent_state(&self, window: &WindowElement) -> Option<SplitState> {
+        let LayoutNode::Split(split) = self else {
+            return None;
+        };
+        if split.position(window).is_some() {
+            return Some(split.state);
+        }
+        split
+            .children
+            .iter()
+            .find_map(|child| child.parent_state(window))
+    }
+
+    /// Set the mode of the container the window is a child of, None if
+    /// the window is not in this node
+    fn set_parent_mode(&mut self, window: &WindowElement, mode: ContainerMode) -> Option<bool> {
+        let LayoutNode::Split(split) = self else {
+            return None;
+        };
+        if let Some(index) = split.position(window) {
+            let changed = split.mode != mode;
+            split.mode = mode;
+            split.selected = index;
+            return Some(changed);
+        }
+        split
+            .children
+            .iter_mut()
+            .find_map(|child| child.set_parent_mode(window, mode))
+    }
+
+    /// Select the tabs leading to the window, None if the window is not in
+    /// this node, else whether a tab changed
+    fn select(&mut self, window: &WindowElement) -> Option<bool> {
+        let split = match self {
+            LayoutNode::Leaf(leaf) => return (leaf == window).then_some(false),
+            LayoutNode::Split(split) => split,
+        };
+        let (index, changed) = split
+            .children
+            .iter_mut()
+            .enumerate()
+            .find_map(|(index, child)| child.select(window).map(|changed| (index, changed)))?;
+        if split.mode == ContainerMode::Split || split.selected == index {
+            return Some(changed);
+        }
+        split.selected = index;
+        Some(true)
+    }
+
+    /// The window shown for the node in a tab
+    fn tab_window(&self) -> WindowElement {
+        match self {
+            LayoutNode::Leaf(leaf) => leaf.clone(),
+            LayoutNode::Split(split) => match split.mode {
+                ContainerMode::Split => split.children[0].tab_window(),
+                _ => split.children[split.selected].tab_window(),
+            },
+        }
+    }
+
+    fn contains(&self, window: &WindowElement) -> bool {
+        match self {
+            LayoutNode::Leaf(leaf) => leaf == window,
+            LayoutNode::Split(split) => split.children.iter().any(|child| child.contains(window)),
+        }
+    }
+
+    fn find(&self, f: &impl Fn(&WindowElement) -> bool) -> Option<&WindowElement> {
+        match self {
+            LayoutNode::Leaf(leaf) => f(leaf).then_some(leaf),
+            LayoutNode::Split(split) => split.children.iter().find_map(|child| child.find(f)),
+        }
+    }
+
+    fn last_window(&self) -> WindowElement {
+        match self {
+            LayoutNode::Leaf(leaf) => leaf.clone(),
+            LayoutNode::Split(split) => split.children.last().unwrap().last_window(),
+        }
+    }
+
+    fn for_each_window_mut(&mut self, f: &mut impl FnMut(&mut WindowElement)) {
+        match self {
+            LayoutNode::Leaf(leaf) => f(leaf),
+            LayoutNode::Split(split) => split
+                .children
+                .iter_mut()
+                .for_each(|child| child.for_each_window_mut(f)),
+        }
+    }
+
+    fn windows(&self, output: &mut Vec<WindowElement>) {
+        match self {
+            LayoutNode::Leaf(leaf) => output.push(leaf.clone()),
+            LayoutNode::Split(split) => split
+                .children
+                .iter()
+                .for_each(|child| child.windows(output)),
+        }
+    }
+
+    /// Insert window next to target, return the window back if target is not
+    /// in this node
+    fn insert(
+        &mut self,
+        target: &WindowElement,
+        window: WindowElement,
+        state: SplitState,
+    ) -> Option<WindowElement> {
+        match self {
+            LayoutNode::Leaf(leaf) => {
+                if leaf != target {
+                    return Some(window);
+                }
+                let leaf = leaf.clone();
+                *self = LayoutNode::Split(SplitNode::new(
+                    state,
+                    vec![LayoutNode::Leaf(leaf), LayoutNode::Leaf(window)],
+                ));
+                None
+            }
+            LayoutNode::Split(split) => {
+                if let Some(index) = split.position(target) {
+                    // tabbed and stacked containers take the window as a new tab
+                    if split.state == state || split.mode != ContainerMode::Split {
+                        split.split_child(index, window);
+                        return None;
+                    }
+                    return split.children[index].insert(target, window, state);
+                }
+                let mut window = window;
+                for child in split.children.iter_mut() {
+                    match child.insert(target, window, state) {
+                        None => return None,
+                        Some(back) => window = back,
+                    }
+                }
+                Some(window)
+            }
+        }
+    }
+
+    /// Remove the window from this node, containers left with only one
+    /// child are replaced by that child
+    fn remove(&mut self, window: &WindowElement) -> bool {
+        let LayoutNode::Split(split) = self else {
+            return false;
+        };
+        let removed = if let Some(index) = split.position(window) {
+            split.remove_child(index);
+            true
+        } else {
+            split.children.iter_mut().any(|child| child.remove(window))
+        };
+        if removed && split.children.len() == 1 {
+            let child = split.children.pop().unwrap();
+            *self = child;
+        }
+        removed
+    }
+
+    /// Lay the node into area, the windows of a node which is not shown
+    /// are hidden and its tabs are not drawn
+    fn arrange(&self, area: Rectangle<i32, Logical>, shown: bool, output: &mut Arrangement) {
+        let split = match self {
+            LayoutNode::Leaf(leaf) => {
+                match shown {
+                    true => output.tiles.push((leaf.clone(), area)),
+                    false => output.hidden.push((leaf.clone(), area)),
+                }
+                return;
+            }
+            LayoutNode::Split(split) => split,
+        };
+        if split.mode == ContainerMode::Split {
+            let areas = split.child_areas(area, output.gap);
+            for (child, child_area) in split.children.iter().zip(areas) {
+                child.arrange(child_area, shown, output);
+            }
+            return;
+        }
+        if shown {
+            for (index, (child, geometry)) in
+                split.children.iter().zip(split.tab_areas(area)).enumerate()
+            {
+                output.tabs.push(Tab {
+                    window: child.tab_window(),
+                    geometry,
+                    selected: index == split.selected,
+                });
+            }
+        }
+        let areas = split.child_areas(area, output.gap);
+        for (index, (child, child_area)) in split.children.iter().zip(areas).enumerate() {
+            child.arrange(child_area, shown && index == split.selected, output);
+        }
+    }
+
+    /// Move the edge of the window on the side given by `after` by delta
+    /// pixels, in the innermost container split with the state which has a
+    /// neighbour of the window on that side. None if the window is not in
+    /// this node, else whether the edge was moved
+    fn resize(
+        &mut self,
+        area: Rectangle<i32, Logical>,
+        window: &WindowElement,
+        state: SplitState,
+        after: bool,
+        delta: f64,
+    ) -> Option<bool> {
+        let split = match self {
+            LayoutNode::Leaf(leaf) => return (leaf == window).then_some(false),
+            LayoutNode::Split(split) => split,
+        };
+        // the gaps are left out, the edge moves a bit less than the pointer
+        let areas = split.child_areas(area, 0);
+        let (index, resized) = split.children.iter_mut().zip(areas).enumerate().find_map(
+            |(index, (child, child_area))| {
+                child
+                    .resize(child_area, window, state, after, delta)
+                    .map(|resized| (index, resized))
+            },
+        )?;
+        // the children of tabbed and stacked containers share the same area
+        if resized || split.state != state || split.mode != ContainerMode::Split {
+            return Some(resized);
+        }
+        let (first, second) = match after {
+            true if index + 1 < split.children.len() => (index, index + 1),
+            false if index > 0 => (index - 1, index),
+            _ => return Some(false),
+        };
+        let total = match state {
+            SplitState::H => area.size.w,
+            SplitState::V => area.size.h,
+        };
+        if total <= 0 {
+            return Some(true);
+        }
+        // the edge between first and second moves, first grows when it
+        // moves right or down
+        let delta = (delta / total as f64)
+            .max(MIN_RATIO - split.ratios[first])
+            .min(split.ratios[second] - MIN_RATIO);
+        split.ratios[first] += delta;
+        split.ratios[second] -= delta;
+        Some(true)
+    }
+}
+
+/// The tile tree of the windows, the geometry of every window in Tile mode
+/// is computed from it
+#[derive(Debug, Default)]
+pub struct LayoutTree {
+    root: Option<LayoutNode>,
+}
+
+impl LayoutTree {
+    pub fn windows(&self) -> Vec<WindowElement> {
+        let mut output = Vec::new();
+        if let Some(root) = &self.root {
+            root.windows(&mut output);
+        }
+        output
+    }
+
+    /// The first window of the tree for which f is true
+    pub fn find(&self, f: impl Fn(&WindowElement) -> bool) -> Option<&WindowElement> {
+        self.root.as_ref()?.find(&f)
+    }
+
+    /// Insert a window next to target, splitting the area of target with the
+    /// state. If target is None or not in the tree, the last window is used
+    pub fn insert(
+        &mut self,
+        window: WindowElement,
+        target: Option<&WindowElement>,
+        state: SplitState,
+    ) {
+        let Some(root) = &mut self.root else {
+            self.root = Some(LayoutNode::Leaf(window));
+            return;
+        };
+        let target = match target {
+            Some(target) if root.contains(target) => target.clone(),
+            _ => root.last_window(),
+        };
+        let back = root.insert(&target, window, state);
+        debug_assert!(back.is_none());
+    }
+
+    /// Exchange the places of the two windows, false if one is not in the tree
+    pub fn swap(&mut self, first: &WindowElement, second: &WindowElement) -> bool {
+        let Some(root) = &mut self.root else {
+            return false;
+        };
+        if !root.contains(first) || !root.contains(second) {
+            return false;
+        }
+        root.for_each_window_mut(&mut |window| {
+            if window == first {
+                *window = second.clone();
+            } else if window == second {
+                *window = first.clone();
+            }
+        });
+        true
+    }
+
+    /// Put the window at the first place, the one there goes where the
+    /// window was. The first window is swapped with the second one
+    pub fn promote(&mut self, window: &WindowElement) -> bool {
+        let windows = self.windows();
+        let master = match windows.first() {
+            Some(first) if first == window => windows.get(1),
+            first => first,
+        };
+        match master {
+            Some(master) => self.swap(window, &master.clone()),
+            None => false,
+        }
+    }
+
+    /// Take the window out of its container and put it after target, in the
+    /// container of target
+    pub fn move_to(&mut self, window: &WindowElement, target: &WindowElement) -> bool {
+        if window == target || !self.windows().contains(window) {
+            return false;
+        }
+        let Some(state) = self
+            .root
+            .as_ref()
+            .and_then(|root| root.parent_state(target))
+        else {
+            return false;
+        };
+        self.remove(window);
+        self.insert(window.clone(), Some(target), state);
+        true
+    }
+
+    pub fn remove(&mut self, window: &WindowElement) -> bool {
+        if matches!(&self.root, Some(LayoutNode::Leaf(leaf)) if leaf == window) {
+            self.root = None;
+            return true;
+        }
+        let removed = self
+            .root
+            .as_mut()
+            .map(|root| root.remove(window))
+            .unwrap_or(false);
+        // the container a lone window was put in is left empty
+        if matches!(&self.root, Some(LayoutNode::Split(split)) if split.children.is_empty()) {
+            self.root = None;
+        }
+        removed
+    }
+
+    /// Show the children of the container of the window with the mode, a
+    /// window alone in the tree is put in a container of its own
+    pub fn set_container_mode(&mut self, window: &WindowElement, mode: ContainerMode) -> bool {
+        match &mut self.root {
+            Some(LayoutNode::Leaf(leaf)) if leaf == window => {
+                if mode == ContainerMode::Split {
+                    return false;
+                }
+                let mut split = SplitNode::new(SplitState::H, vec![LayoutNode::Leaf(leaf.clone())]);
+                split.mode = mode;
+                self.root = Some(LayoutNode::Split(split));
+                true
+            }
+            Some(root) => root.set_parent_mode(window, mode).unwrap_or(false),
+            None => false,
+        }
+    }
+
+    /// Select the tabs showing the window, return true if one changed
+    pub fn select(&mut self, window: &WindowElement) -> bool {
+        self.root
+            .as_mut()
+            .and_then(|root| root.select(window))
+            .unwrap_or(false)
+    }
+
+    /// Move the right or bottom edge (`after`) or the left or top edge of
+    /// the window by delta pixels, return false if it has no neighbour there
+    pub fn resize(
+        &mut self,
+        area: Rectangle<i32, Logical>,
+        window: &WindowElement,
+        state: SplitState,
+        after: bool,
+        delta: f64,
+    ) -> bool {
+        self.root
+            .as_mut()
+            .and_then(|root| root.resize(area, window, state, after, delta))
+            .unwrap_or(false)
+    }
+
+    /// Compute the area of every window shown when the tree is laid into area
+    pub fn geometries(
+        &self,
+        area: Rectangle<i32, Logical>,
+    ) -> Vec<(WindowElement, Rectangle<i32, Logical>)> {
+        self.arrange(area, 0).tiles
+    }
+
+    /// Lay the tree into area with gap between the tiles, with the windows
+    /// behind other tabs and the tabs to draw
+    pub fn arrange(&self, area: Rectangle<i32, Logical>, gap: i32) -> Arrangement {
+        let mut output = Arrangement {
+            gap,
+            ..Default::default()
+        };
+        if let Some(root) = &self.root {
+            root.arrange(area, true, &mut output);
+        }
+        output
+    }
+}
+
+/// Lay the windows out with the first count ones side by side vertically
+/// on the left part of area, ratio of its width, and the others stacked on
+/// the right, with gap between them. Without masters or without the others
+/// one column takes area
+pub fn master_geometries(
+    windows: &[WindowElement],
+    area: Rectangle<i32, Logical>,
+    ratio: f64,
+    count: usize,
+    gap: i32,
+) -> Vec<(WindowElement, Rectangle<i32, Logical>)> {
+    let count = count.min(windows.len());
+    let (masters, stack) = windows.split_at(count);
+    let (master_width, stack_x) = if masters.is_empty() {
+        (0, 0)
+    } else if stack.is_empty() {
+        (area.size.w, area.size.w)
+    } else {
+        let width = ((area.size.w - gap) as f64 * ratio).round() as i32;
+        (width, width + gap)
+    };
+    let column = |windows: &[WindowElement], x: i32, w: i32| {
+        let len = windows.len() as i32;
+        let height = area.size.h - gap * (len - 1);
+        windows
+            .iter()
+            .enumerate()
+            .map(|(index, window)| {
+                let index = index as i32;
+                let y = height * index / len;
+                let h = height * (index + 1) / len - y;
+                let y = y + gap * index;
+                (
+                    window.clone(),
+                    Rectangle::from_loc_and_size((area.loc.x + x, area.loc.y + y), (w, h)),
+                )
+            })
+            .collect::<Vec<_>>()
+    };
+    let mut output = column(masters, 0, master_width);
+    output.extend(column(stack, stack_x, area.size.w - stack_x));
+    output
+}
+
+/// Lay the windows out as columns of the height of area, width of its
+/// width, from the left of a strip of which area shows from scroll. The
+/// columns are gap apart
+pub fn column_geometries(
+    windows: &[WindowElement],
+    area: Rectangle<i32, Logical>,
+    width: f64,
+    scroll: i32,
+    gap: i32,
+) -> Vec<(WindowElement, Rectangle<i32, Logical>)> {
+    let width = (area.size.w as f64 * width).round() as i32;
+    windows
+        .iter()
+        .enumerate()
+        .map(|(index, window)| {
+            let x = area.loc.x + (width + gap) * index as i32 - scroll;
+            (
+                window.clone(),
+                Rectangle::from_loc_and_size((x, area.loc.y), (width, area.size.h)),
+            )
+        })
+        .collect()
+}
+
+/// The edges of the tile which another tile touches, or is at most gap
+/// away from
+pub fn touching_edges(
+    tile: Rectangle<i32, Logical>,
+    tiles: &[(WindowElement, Rectangle<i32, Logical>)],
+    gap: i32,
+) -> ResizeEdge {
+    let touch = |end: i32, start: i32| (0..=gap).contains(&(start - end));
+    let mut edges = ResizeEdge::empty();
+    for (_, other) in tiles.iter().filter(|(_, other)| *other != tile) {
+        let overlaps_x =
+            other.loc.x < tile.loc.x + tile.size.w && tile.loc.x < other.loc.x + other.size.w;
+        let overlaps_y =
+            other.loc.y < tile.loc.y + tile.size.h && tile.loc.y < other.loc.y + other.size.h;
+        if overlaps_y && touch(other.loc.x + other.size.w, tile.loc.x) {
+            edges |= ResizeEdge::LEFT;
+        }
+        if overlaps_y && touch(tile.loc.x + tile.size.w, other.loc.x) {
+            edges |= ResizeEdge::RIGHT;
+        }
+        if overlaps_x && touch(other.loc.y + other.size.h, tile.loc.y) {
+            edges |= ResizeEdge::TOP;
+        }
+        if overlaps_x && touch(tile.loc.y + tile.size.h, other.loc.y) {
+            edges |= ResizeEdge::BOTTOM;
+        }
+    }
+    edges
+}
+
+impl<BackendData: Backend + 'static> FlyJa<BackendData> {
+    /// Exchange the focused tile with its neighbour in the direction
+    pub fn swap_focused(&mut self, direction: Direction) {
+        let Some((window, neighbour)) = self.focused_tile_neighbour(direction) else {
+            return;
+        };
+        if self.workspace_mut().layout.swap(&window, &neighbour) {
+            self.apply_layout();
+        }
+    }
+
+    /// Move the focused tile into the container of its neighbour in the
+    /// direction
+    pub fn move_focused(&mut self, direction: Direction) {
+        let Some((window, neighbour)) = self.focused_tile_neighbour(direction) else {
+            return;
+        };
+        if self.workspace_mut().layout.move_to(&window, &neighbour) {
+            self.apply_layout();
+        }
+    }
+
+    /// Put the focused tile at the master position, the first one of the tree
+    pub fn promote_focused(&mut self) {
+        if !self.workspace().wmstatus.is_tiled() {
+            return;
+        }
+        let Some(window) = self.focused_window() else {
+            return;
+        };
+        if self.workspace_mut().layout.promote(&window) {
+            self.apply_layout();
+        }
+    }
+
+    /// Show the container of the focused tile split, tabbed or stacked
+    pub fn set_focused_container_mode(&mut self, mode: ContainerMode) {
+        if self.workspace().wmstatus != WmStatus::Tile {
+            return;
+        }
+        let Some(window) = self.focused_window() else {
+            return;
+        };
+        if self
+            .workspace_mut()
+            .layout
+            .set_container_mode(&window, mode)
+        {
+            self.apply_layout();
+        }
+    }
+
+    fn focused_tile_neighbour(
+        &self,
+        direction: Direction,
+    ) -> Option<(WindowElement, WindowElement)> {
+        if !self.workspace().wmstatus.is_tiled() {
+            return None;
+        }
+        let window = self.focused_window()?;
+        let neighbour = self.window_in_direction(&window, direction)?;
+        Some((window, neighbour))
+    }
+}
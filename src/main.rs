@@ -1,19 +1,30 @@
+mod config;
+mod cursor;
+mod focus;
 mod grab;
 mod handle;
+#[cfg(test)]
+mod headless;
 mod input;
+mod ipc;
+mod layout;
+mod render;
+mod rules;
 mod shell;
 mod state;
-
-#[allow(unused)]
+mod tabs;
+#[cfg(test)]
+mod tests;
 mod udev;
 
 mod winit;
+mod workspace;
 
 use state::FlyJa;
 
 use smithay::reexports::wayland_server::Display;
 
-use crate::winit::run_winit;
+use crate::{udev::run_udev, winit::run_winit};
 
 use state::Backend;
 
@@ -22,8 +33,10 @@ pub struct CalloopData<BackendData: Backend + 'static> {
     display: Display<FlyJa<BackendData>>,
 }
 
-static POSSIBLE_BACKENDS: &[&str] =
-    &["--winit : Run flyja as a X11 or Wayland client using winit."];
+static POSSIBLE_BACKENDS: &[&str] = &[
+    "--winit : Run flyja as a X11 or Wayland client using winit.",
+    "--tty-udev : Run flyja as a tty udev client (requires root if without logind).",
+];
 
 fn main() {
     if let Ok(env_filter) = tracing_subscriber::EnvFilter::try_from_default_env() {
@@ -41,6 +54,12 @@ fn main() {
             tracing::info!("Starting flyja with winit backend");
             run_winit().unwrap();
         }
+        Some("--tty-udev") => {
+            tracing::info!("Starting flyja on a tty using udev");
+            if let Err(err) = run_udev() {
+                tracing::error!("Failed to run the udev backend: {}", err);
+            }
+        }
         Some(other) => {
             tracing::error!("Unknown backend: {}", other);
         }
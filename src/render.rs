@@ -0,0 +1,161 @@
+use smithay::{
+    backend::renderer::{
+        element::{surface::WaylandSurfaceRenderElement, AsRenderElements, RenderElement, Wrap},
+        ImportAll, ImportMem, Renderer,
+    },
+    desktop::{layer_map_for_output, Space},
+    output::Output,
+    render_elements,
+    utils::{Logical, Point, Scale},
+    wayland::shell::wlr_layer::Layer,
+};
+
+use crate::{
+    cursor::{PointerElement, PointerRenderElement},
+    shell::{fullscreen_elements, window_elements, WindowElement, WindowRenderElement},
+    tabs::{TabBars, TabRenderElement},
+};
+
+render_elements!(
+    pub OutputRenderElements<R, E> where R: ImportAll + ImportMem;
+    Layer=WaylandSurfaceRenderElement<R>,
+    Window=Wrap<E>,
+    Pointer=PointerRenderElement<R>,
+    Tab=TabRenderElement<R>,
+);
+
+impl<R, E> std::fmt::Debug for OutputRenderElements<R, E>
+where
+    R: Renderer + ImportAll + ImportMem,
+    E: RenderElement<R> + std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Layer(arg0) => f.debug_tuple("Layer").field(arg0).finish(),
+            Self::Window(arg0) => f.debug_tuple("Window").field(arg0).finish(),
+            Self::Pointer(arg0) => f.debug_tuple("Pointer").field(arg0).finish(),
+            Self::Tab(arg0) => f.debug_tuple("Tab").field(arg0).finish(),
+            Self::_GenericCatcher(arg0) => f.debug_tuple("_GenericCatcher").field(arg0).finish(),
+        }
+    }
+}
+
+/// What is drawn on the output, from the top: the pointer, then the
+/// fullscreen window of the output alone, or the Overlay and Top layers,
+/// the floating windows, the tabs, the tiled windows and the Bottom and
+/// Background layers
+pub fn output_elements<R>(
+    renderer: &mut R,
+    space: &Space<WindowElement>,
+    output: &Output,
+    pointer: &PointerElement<R::TextureId>,
+    pointer_location: Point<f64, Logical>,
+    tabs: &TabBars<R::TextureId>,
+) -> Option<Vec<OutputRenderElements<R, WindowRenderElement<R>>>>
+where
+    R: Renderer + ImportAll + ImportMem,
+    <R as Renderer>::TextureId: Clone + 'static,
+{
+    let output_geometry = space.output_geometry(output)?;
+    let scale = Scale::from(output.current_scale().fractional_scale());
+    let mut elements: Vec<OutputRenderElements<R, WindowRenderElement<R>>> = Vec::new();
+    if output_geometry.to_f64().contains(pointer_location) {
+        let location = (pointer_location - output_geometry.loc.to_f64())
+            .to_physical(scale)
+            .to_i32_round()
+            - pointer.hotspot().to_physical_precise_round(scale);
+        elements.extend(pointer.render_elements(renderer, location, scale, 1.0));
+    }
+    if let Some(window_elements) = fullscreen_elements(renderer, space, output) {
+        elements.extend(
+            window_elements
+                .into_iter()
+                .map(|element| OutputRenderElements::Window(Wrap::from(element))),
+        );
+        return Some(elements);
+    }
+
+    elements.extend(layer_elements(
+        renderer,
+        output,
+        &[Layer::Overlay, Layer::Top],
+    ));
+    // the floating windows are kept above the tiles, see `raise_floating`
+    let (floating, tiled): (Vec<&WindowElement>, Vec<&WindowElement>) = space
+        .elements()
+        .filter(|window| {
+            space
+                .element_bbox(window)
+                .map_or(false, |bbox| bbox.overlaps(output_geometry))
+        })
+        .partition(|window| window.is_floating());
+    for window in floating.into_iter().rev() {
+        elements.extend(space_window_elements(renderer, space, output, window));
+    }
+    elements.extend(
+        tabs.render_elements(output_geometry, scale)
+            .into_iter()
+            .map(OutputRenderElements::Tab),
+    );
+    for window in tiled.into_iter().rev() {
+        elements.extend(space_window_elements(renderer, space, output, window));
+    }
+    elements.extend(layer_elements(
+        renderer,
+        output,
+        &[Layer::Bottom, Layer::Background],
+    ));
+    Some(elements)
+}
+
+fn space_window_elements<R>(
+    renderer: &mut R,
+    space: &Space<WindowElement>,
+    output: &Output,
+    window: &WindowElement,
+) -> Vec<OutputRenderElements<R, WindowRenderElement<R>>>
+where
+    R: Renderer + ImportAll + ImportMem,
+    <R as Renderer>::TextureId: Clone + 'static,
+{
+    window_elements(renderer, space, output, window)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|element| OutputRenderElements::Window(Wrap::from(element)))
+        .collect()
+}
+
+/// The surfaces of the layers of the output, in the order of `layers` and
+/// the last mapped on top
+fn layer_elements<R>(
+    renderer: &mut R,
+    output: &Output,
+    layers: &[Layer],
+) -> Vec<OutputRenderElements<R, WindowRenderElement<R>>>
+where
+    R: Renderer + ImportAll + ImportMem,
+    <R as Renderer>::TextureId: Clone + 'static,
+{
+    let scale = Scale::from(output.current_scale().fractional_scale());
+    let map = layer_map_for_output(output);
+    let mut elements = Vec::new();
+    for layer in layers {
+        for surface in map.layers_on(*layer).rev() {
+            let Some(geometry) = map.layer_geometry(surface) else {
+                continue;
+            };
+            elements.extend(
+                surface
+                    .render_elements::<WaylandSurfaceRenderElement<R>>(
+                        renderer,
+                        geometry.loc.to_physical_precise_round(scale),
+                        scale,
+                        1.0,
+                    )
+                    .into_iter()
+                    .map(OutputRenderElements::Layer),
+            );
+        }
+    }
+    elements
+}
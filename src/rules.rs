@@ -0,0 +1,132 @@
+//! Window rules: what happens to the windows whose app_id and title match
+//! the patterns of a `[[rules]]` table of the config
+
+use regex::Regex;
+use serde::Deserialize;
+use smithay::utils::{Logical, Size};
+
+use crate::{shell::WindowElement, state::Backend, FlyJa};
+
+/// A regex matched against a property of the window
+#[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Pattern(Regex);
+
+impl TryFrom<String> for Pattern {
+    type Error = String;
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Regex::new(&value)
+            .map(Pattern)
+            .map_err(|err| format!("invalid pattern `{value}`: {err}"))
+    }
+}
+
+impl Pattern {
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+
+    /// a window without the property does not match
+    fn matches(&self, value: Option<&str>) -> bool {
+        value.map_or(false, |value| self.0.is_match(value))
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WindowRule {
+    /// the patterns not given match every window
+    pub app_id: Option<Pattern>,
+    pub title: Option<Pattern>,
+    /// float above the tiles, or take a tile even if the window would float
+    pub floating: Option<bool>,
+    /// the workspace the window is moved to, numbered from 1
+    pub workspace: Option<usize>,
+    /// width and height of the window with its border while it is not tiled
+    pub size: Option<(i32, i32)>,
+    /// draw the border around the window
+    pub border: Option<bool>,
+}
+
+impl WindowRule {
+    pub fn matches(&self, app_id: Option<&str>, title: Option<&str>) -> bool {
+        self.app_id
+            .as_ref()
+            .map_or(true, |pattern| pattern.matches(app_id))
+            && self
+                .title
+                .as_ref()
+                .map_or(true, |pattern| pattern.matches(title))
+    }
+}
+
+impl<BackendData: Backend + 'static> FlyJa<BackendData> {
+    /// indices of the rules of the config matching the window
+    pub fn matching_rules(&self, window: &WindowElement) -> Vec<usize> {
+        let app_id = window.app_id();
+        let title = window.title();
+        self.config
+            .rules
+            .iter()
+            .enumerate()
+            .filter(|(_, rule)| rule.matches(app_id.as_deref(), title.as_deref()))
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    /// Apply the rules the window started to match, in their order. A rule
+    /// is applied again only once the window stopped matching it, so a
+    /// window moved away by the user stays where it is while its title
+    /// keeps changing. After a reload every rule it matches is new
+    pub fn apply_window_rules(&mut self, window: &WindowElement) {
+        let matching = self.matching_rules(window);
+        let previous = window.replace_rules(self.config.generation, matching.clone());
+        let rules: Vec<WindowRule> = matching
+            .into_iter()
+            .filter(|index| !previous.contains(index))
+            .map(|index| self.config.rules[index].clone())
+            .collect();
+        if rules.is_empty() {
+            return;
+        }
+        for rule in rules {
+            self.apply_window_rule(window, &rule);
+        }
+        self.apply_layout();
+    }
+
+    fn apply_window_rule(&mut self, window: &WindowElement, rule: &WindowRule) {
+        if let Some(border) = rule.border {
+            window.set_border_enabled(border);
+        }
+        if let Some(floating) = rule.floating {
+            self.set_window_floating(window, floating);
+        }
+        if let Some(workspace) = rule.workspace {
+            match workspace
+                .checked_sub(1)
+                .filter(|index| *index < self.workspaces.len())
+            {
+                Some(index) => self.move_window_to_workspace(window, index),
+                None => tracing::warn!("No workspace {} for the window rule", workspace),
+            }
+        }
+        if let Some(size) = rule.size {
+            self.resize_untiled_window(window, size.into());
+        }
+    }
+
+    /// Ask the window to take the size with its border, unless flyja
+    /// decides its size
+    fn resize_untiled_window(&mut self, window: &WindowElement, size: Size<i32, Logical>) {
+        if self.is_tiled_window(window) || window.is_fullscreen() || window.is_maximized() {
+            return;
+        }
+        window.toplevel().with_pending_state(|state| {
+            state.size = Some(window.content_size(size));
+        });
+        if window.initial_configure_sent() {
+            window.toplevel().send_pending_configure();
+        }
+    }
+}
@@ -1,24 +1,78 @@
-use std::time::Duration;
+use std::{
+    cell::RefCell,
+    rc::Rc,
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
 
 use smithay::{
     backend::renderer::{
         element::{
-            solid::SolidColorRenderElement, surface::WaylandSurfaceRenderElement, AsRenderElements,
+            solid::{SolidColorBuffer, SolidColorRenderElement},
+            surface::WaylandSurfaceRenderElement,
+            AsRenderElements, Kind,
         },
         ImportAll, ImportMem, Renderer,
     },
     desktop::{space::SpaceElement, Space, Window, WindowSurfaceType},
     output::Output,
-    reexports::wayland_server::protocol::wl_surface,
+    reexports::{
+        wayland_protocols::xdg::shell::server::xdg_toplevel, wayland_server::protocol::wl_surface,
+    },
     render_elements,
     utils::{IsAlive, Logical, Physical, Point, Rectangle, Scale, Size},
-    wayland::{compositor::SurfaceData, seat::WaylandFocus, shell::xdg::ToplevelSurface},
+    wayland::{
+        compositor::{with_states, SurfaceData},
+        seat::WaylandFocus,
+        shell::xdg::{SurfaceCachedState, ToplevelSurface, XdgToplevelSurfaceData},
+    },
 };
 
+use crate::{config::BorderConfig, grab::resize_grab::ResizeEdge};
+
+static NEXT_WINDOW_ID: AtomicU64 = AtomicU64::new(1);
+
+/// The border flyja draws around a window
+#[derive(Debug)]
+struct Border {
+    config: BorderConfig,
+    /// a window rule can turn the border off
+    enabled: bool,
+    /// top, bottom, left and right sides
+    buffers: [SolidColorBuffer; 4],
+}
+
+/// The window rules a window matched, by their index in the rules of a
+/// config generation
+#[derive(Debug, Default)]
+struct MatchedRules {
+    generation: u64,
+    rules: Vec<usize>,
+}
+
+/// Where a window is put instead of its tile or floating geometry
+#[derive(Debug, Default)]
+struct Placement {
+    /// the output the window covers
+    fullscreen: Option<Output>,
+    /// the window fills the usable area of its output
+    maximized: bool,
+    /// the geometry to go back to once neither is set
+    restore: Option<Rectangle<i32, Logical>>,
+    /// the window floats above the tiles in the tiled modes
+    floating: bool,
+    /// the window is centered once its size is known
+    center: bool,
+}
+
 #[derive(Debug, Clone)]
 pub struct WindowElement {
     window: Window,
-    pub resize_size: Option<(i32, i32)>,
+    id: u64,
+    border: Rc<RefCell<Border>>,
+    placement: Rc<RefCell<Placement>>,
+    /// the window rules the window matched last time
+    rules: Rc<RefCell<MatchedRules>>,
 }
 
 impl PartialEq for WindowElement {
@@ -28,33 +82,219 @@ impl PartialEq for WindowElement {
 }
 
 impl WindowElement {
-    pub fn remap_element(&self, space: &mut Space<Self>) {
-        let Some(position) = space.element_location(self) else {
-            return;
-        };
-        space.map_element(self.clone(), position, true);
+    pub fn new(surface: ToplevelSurface, border: &BorderConfig) -> Self {
+        WindowElement {
+            window: Window::new(surface),
+            id: NEXT_WINDOW_ID.fetch_add(1, Ordering::Relaxed),
+            border: Rc::new(RefCell::new(Border {
+                config: border.clone(),
+                enabled: true,
+                buffers: Default::default(),
+            })),
+            placement: Rc::default(),
+            rules: Rc::default(),
+        }
     }
 
-    pub fn set_resize_size(&self, resize_size: (i32, i32)) -> Self {
-        WindowElement {
-            resize_size: Some(resize_size),
-            ..self.clone()
+    pub fn set_border(&self, border: &BorderConfig) {
+        self.border.borrow_mut().config = border.clone();
+    }
+
+    pub fn set_border_enabled(&self, enabled: bool) {
+        self.border.borrow_mut().enabled = enabled;
+    }
+
+    /// fullscreen windows and the ones with the border turned off have none
+    pub fn border_width(&self) -> i32 {
+        let border = self.border.borrow();
+        if self.is_fullscreen() || !border.enabled {
+            return 0;
         }
+        border.config.width.max(0)
     }
-}
 
-impl WindowElement {
-    pub fn new(surface: ToplevelSurface) -> Self {
-        WindowElement {
-            window: Window::new(surface),
-            resize_size: None,
+    /// Remember the indices of the window rules of the config generation
+    /// the window matches, the previous ones are returned if they are of
+    /// the same generation
+    pub fn replace_rules(&self, generation: u64, rules: Vec<usize>) -> Vec<usize> {
+        let previous = std::mem::replace(
+            &mut *self.rules.borrow_mut(),
+            MatchedRules { generation, rules },
+        );
+        if previous.generation == generation {
+            previous.rules
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// The indices of the window rules of the config generation the window
+    /// matched
+    pub fn rules(&self, generation: u64) -> Vec<usize> {
+        let matched = self.rules.borrow();
+        if matched.generation == generation {
+            matched.rules.clone()
+        } else {
+            Vec::new()
+        }
+    }
+
+    pub fn fullscreen_output(&self) -> Option<Output> {
+        self.placement.borrow().fullscreen.clone()
+    }
+
+    pub fn is_fullscreen(&self) -> bool {
+        self.placement.borrow().fullscreen.is_some()
+    }
+
+    pub fn is_maximized(&self) -> bool {
+        self.placement.borrow().maximized
+    }
+
+    /// Cover the output, or leave the fullscreen with None
+    pub fn set_fullscreen(&self, output: Option<Output>) {
+        let fullscreen = output.is_some();
+        self.placement.borrow_mut().fullscreen = output;
+        self.toplevel().with_pending_state(|state| {
+            if fullscreen {
+                state.states.set(xdg_toplevel::State::Fullscreen);
+            } else {
+                state.states.unset(xdg_toplevel::State::Fullscreen);
+                state.fullscreen_output = None;
+            }
+        });
+    }
+
+    pub fn set_maximized(&self, maximized: bool) {
+        self.placement.borrow_mut().maximized = maximized;
+        self.toplevel().with_pending_state(|state| {
+            if maximized {
+                state.states.set(xdg_toplevel::State::Maximized);
+            } else {
+                state.states.unset(xdg_toplevel::State::Maximized);
+            }
+        });
+    }
+
+    pub fn is_floating(&self) -> bool {
+        self.placement.borrow().floating
+    }
+
+    pub fn set_floating(&self, floating: bool) {
+        let mut placement = self.placement.borrow_mut();
+        placement.floating = floating;
+        placement.center = floating;
+    }
+
+    /// Center the window once the client committed its first buffer
+    pub fn center_when_sized(&self) {
+        self.placement.borrow_mut().center = true;
+    }
+
+    /// Whether the window is still to be centered, it is only centered once
+    pub fn take_center(&self) -> bool {
+        std::mem::take(&mut self.placement.borrow_mut().center)
+    }
+
+    /// Dialogs, windows with a parent, and windows which cannot be resized
+    /// float rather than take a tile
+    pub fn wants_floating(&self) -> bool {
+        if self.toplevel().parent().is_some() {
+            return true;
         }
+        with_states(self.toplevel().wl_surface(), |states| {
+            let state = states.cached_state.current::<SurfaceCachedState>();
+            state.min_size.w > 0 && state.min_size == state.max_size
+        })
+    }
+
+    /// The client committed a buffer, the size of the window is known
+    pub fn has_contents(&self) -> bool {
+        let size = SpaceElement::geometry(&self.window).size;
+        size.w > 0 && size.h > 0
+    }
+
+    /// Tell the client which of its edges are against another tile
+    pub fn set_tiled_edges(&self, edges: ResizeEdge) {
+        let states = [
+            (ResizeEdge::LEFT, xdg_toplevel::State::TiledLeft),
+            (ResizeEdge::RIGHT, xdg_toplevel::State::TiledRight),
+            (ResizeEdge::TOP, xdg_toplevel::State::TiledTop),
+            (ResizeEdge::BOTTOM, xdg_toplevel::State::TiledBottom),
+        ];
+        self.toplevel().with_pending_state(|state| {
+            for (edge, tiled) in states {
+                if edges.contains(edge) {
+                    state.states.set(tiled);
+                } else {
+                    state.states.unset(tiled);
+                }
+            }
+        });
+    }
+
+    /// Remember the geometry to give back when the window is neither
+    /// fullscreen nor maximized anymore
+    pub fn set_restore_geometry(&self, geometry: Rectangle<i32, Logical>) {
+        self.placement.borrow_mut().restore = Some(geometry);
+    }
+
+    pub fn take_restore_geometry(&self) -> Option<Rectangle<i32, Logical>> {
+        self.placement.borrow_mut().restore.take()
+    }
+
+    /// The size of the surface for the window to take the size with its border
+    pub fn content_size(&self, size: Size<i32, Logical>) -> Size<i32, Logical> {
+        let width = self.border_width();
+        ((size.w - 2 * width).max(1), (size.h - 2 * width).max(1)).into()
+    }
+
+    pub fn is_activated(&self) -> bool {
+        self.toplevel()
+            .current_state()
+            .states
+            .contains(xdg_toplevel::State::Activated)
+    }
+
+    /// id of the window used over ipc
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub fn app_id(&self) -> Option<String> {
+        with_states(self.toplevel().wl_surface(), |states| {
+            states
+                .data_map
+                .get::<XdgToplevelSurfaceData>()
+                .unwrap()
+                .lock()
+                .unwrap()
+                .app_id
+                .clone()
+        })
+    }
+
+    pub fn title(&self) -> Option<String> {
+        with_states(self.toplevel().wl_surface(), |states| {
+            states
+                .data_map
+                .get::<XdgToplevelSurfaceData>()
+                .unwrap()
+                .lock()
+                .unwrap()
+                .title
+                .clone()
+        })
     }
 
     pub fn toplevel(&self) -> &ToplevelSurface {
         self.window.toplevel()
     }
 
+    pub fn initial_configure_sent(&self) -> bool {
+        initial_configure_sent(self.toplevel())
+    }
+
     pub fn surface_under<P>(
         &self,
         point: P,
@@ -100,17 +340,17 @@ impl IsAlive for WindowElement {
 }
 
 impl SpaceElement for WindowElement {
+    /// the geometry of the window with its border
     fn geometry(&self) -> Rectangle<i32, smithay::utils::Logical> {
-        let geo = SpaceElement::geometry(&self.window);
-        if let Some((width, height)) = self.resize_size {
-            let geo = Rectangle::from_loc_and_size(geo.loc, Size::from((width, height)));
-            return geo;
-        }
-        geo
+        let mut geometry = SpaceElement::geometry(&self.window);
+        let width = self.border_width();
+        geometry.loc -= (width, width).into();
+        geometry.size += (2 * width, 2 * width).into();
+        geometry
     }
 
     fn bbox(&self) -> Rectangle<i32, smithay::utils::Logical> {
-        SpaceElement::bbox(&self.window)
+        SpaceElement::bbox(&self.window).merge(self.geometry())
     }
 
     fn is_in_input_region(&self, point: &Point<f64, smithay::utils::Logical>) -> bool {
@@ -159,17 +399,113 @@ where
     R: Renderer + ImportAll,
     <R as Renderer>::TextureId: 'static,
 {
-    type RenderElement = WaylandSurfaceRenderElement<R>;
+    type RenderElement = WindowRenderElement<R>;
 
     #[profiling::function]
-    fn render_elements<C: From<WaylandSurfaceRenderElement<R>>>(
+    fn render_elements<C: From<WindowRenderElement<R>>>(
         &self,
         renderer: &mut R,
         location: Point<i32, Physical>,
         scale: Scale<f64>,
         alpha: f32,
     ) -> Vec<C> {
-        self.window
-            .render_elements(renderer, location, scale, alpha)
+        let mut elements: Vec<C> = self
+            .window
+            .render_elements::<WindowRenderElement<R>>(renderer, location, scale, alpha)
+            .into_iter()
+            .map(C::from)
+            .collect();
+
+        let width = self.border_width();
+        if width == 0 {
+            return elements;
+        }
+        let mut border = self.border.borrow_mut();
+        let color = if self.is_activated() {
+            border.config.focused.0
+        } else {
+            border.config.unfocused.0
+        };
+        let geometry = SpaceElement::geometry(self);
+        let (x, y) = (geometry.loc.x, geometry.loc.y);
+        let (w, h) = (geometry.size.w, geometry.size.h);
+        let sides = [
+            Rectangle::from_loc_and_size((x, y), (w, width)),
+            Rectangle::from_loc_and_size((x, y + h - width), (w, width)),
+            Rectangle::from_loc_and_size((x, y + width), (width, h - 2 * width)),
+            Rectangle::from_loc_and_size((x + w - width, y + width), (width, h - 2 * width)),
+        ];
+        for (buffer, side) in border.buffers.iter_mut().zip(sides) {
+            buffer.update(side.size, color);
+            let element = SolidColorRenderElement::from_buffer(
+                buffer,
+                location + side.loc.to_physical_precise_round(scale),
+                scale,
+                alpha,
+                Kind::Unspecified,
+            );
+            elements.push(C::from(WindowRenderElement::from(element)));
+        }
+        elements
     }
 }
+
+/// The toplevel was sent its first configure, the next ones may be sent
+/// right away
+pub fn initial_configure_sent(toplevel: &ToplevelSurface) -> bool {
+    with_states(toplevel.wl_surface(), |states| {
+        states
+            .data_map
+            .get::<XdgToplevelSurfaceData>()
+            .unwrap()
+            .lock()
+            .unwrap()
+            .initial_configure_sent
+    })
+}
+
+/// The topmost window covering the output
+pub fn fullscreen_window(space: &Space<WindowElement>, output: &Output) -> Option<WindowElement> {
+    space
+        .elements()
+        .rev()
+        .find(|window| window.fullscreen_output().as_ref() == Some(output))
+        .cloned()
+}
+
+/// The elements of the window on the output, from its top
+pub fn window_elements<R>(
+    renderer: &mut R,
+    space: &Space<WindowElement>,
+    output: &Output,
+    window: &WindowElement,
+) -> Option<Vec<WindowRenderElement<R>>>
+where
+    R: Renderer + ImportAll + ImportMem,
+    <R as Renderer>::TextureId: 'static,
+{
+    let output_geometry = space.output_geometry(output)?;
+    let location = space.element_location(window)? - window.geometry().loc - output_geometry.loc;
+    let scale = output.current_scale().fractional_scale();
+    Some(window.render_elements::<WindowRenderElement<R>>(
+        renderer,
+        location.to_physical_precise_round(scale),
+        Scale::from(scale),
+        1.0,
+    ))
+}
+
+/// The elements of the fullscreen window of the output, it is drawn alone
+/// so it covers the other windows and the layer surfaces
+pub fn fullscreen_elements<R>(
+    renderer: &mut R,
+    space: &Space<WindowElement>,
+    output: &Output,
+) -> Option<Vec<WindowRenderElement<R>>>
+where
+    R: Renderer + ImportAll + ImportMem,
+    <R as Renderer>::TextureId: 'static,
+{
+    let window = fullscreen_window(space, output)?;
+    window_elements(renderer, space, output, &window)
+}
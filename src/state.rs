@@ -1,23 +1,33 @@
-use std::{ffi::OsString, os::unix::io::AsRawFd, sync::Arc};
+use std::{ffi::OsString, os::unix::io::AsRawFd, sync::Arc, time::Duration};
 
 use smithay::{
     delegate_fractional_scale, delegate_input_method_manager, delegate_text_input_manager,
     delegate_xdg_activation,
-    desktop::{space::SpaceElement, utils::surface_primary_scanout_output, PopupManager, Space, WindowSurfaceType},
+    desktop::{
+        layer_map_for_output,
+        utils::{send_frames_surface_tree, surface_primary_scanout_output},
+        PopupManager, Space, WindowSurfaceType,
+    },
     input::Seat,
-    input::{pointer::PointerHandle, SeatState},
+    input::{
+        pointer::{CursorImageStatus, PointerHandle},
+        SeatState,
+    },
+    output::Output,
     reexports::{
         calloop::{generic::Generic, EventLoop, Interest, LoopSignal, Mode, PostAction},
-        wayland_protocols::xdg::shell::server::xdg_toplevel,
         wayland_server::{backend::ClientData, protocol::wl_surface::WlSurface, Display},
     },
-    utils::{Logical, Point, Size},
+    utils::{IsAlive, Logical, Point, Rectangle, SERIAL_COUNTER},
     wayland::{
         compositor::{get_parent, with_states, CompositorClientState, CompositorState},
         data_device::DataDeviceState,
         fractional_scale::{with_fractional_scale, FractionalScaleHandler},
         output::OutputManagerState,
-        shell::xdg::XdgShellState,
+        shell::{
+            wlr_layer::{Layer, WlrLayerShellState},
+            xdg::{decoration::XdgDecorationState, XdgShellState},
+        },
         shm::ShmState,
         socket::ListeningSocketSource,
         xdg_activation::{
@@ -26,41 +36,45 @@ use smithay::{
     },
 };
 
+use serde::Deserialize;
+
 pub trait Backend {
     const HAS_RELATIVE_MOTION: bool = false;
     fn seat_name(&self) -> String;
+    /// Switch to another virtual terminal, only backends with a session
+    /// can do it
+    fn change_vt(&mut self, _vt: i32) {}
 }
 
-use crate::{shell::WindowElement, CalloopData};
+use crate::{
+    config::{init_config_watcher, Config},
+    cursor::Cursor,
+    grab::resize_grab::ResizeEdge,
+    ipc::{
+        init_ipc_listener,
+        protocol::{Event, SOCKET_ENV},
+        IpcState,
+    },
+    layout::{touching_edges, Arrangement, Tab},
+    shell::{fullscreen_window, WindowElement},
+    workspace::{Workspace, WORKSPACE_COUNT},
+    CalloopData,
+};
 
-#[derive(Debug, Default, PartialEq, Eq)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum WmStatus {
     Tile,
+    /// master windows on the left, the others stacked on the right
+    Master,
+    /// a column per window on a strip scrolled to the focused one
+    Scroll,
     #[default]
     Stack,
 }
 
-#[derive(Debug, Default)]
-pub enum PeddingResize {
-    Resizing(WlSurface),
-    ResizeFinished(WlSurface),
-    ResizeTwoWindowFinished((WlSurface, WlSurface)),
-    #[default]
-    Stop,
-}
-
-#[derive(Debug, Default)]
-pub enum WindowRemoved {
-    #[default]
-    NoState,
-    Region {
-        pos_start: (i32, i32),
-        pos_end: (i32, i32),
-    },
-    PeddingMutiResizeFinished(Vec<WlSurface>),
-}
-
-#[derive(Debug, Default, Clone, Copy)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum SplitState {
     #[default]
     H,
@@ -68,12 +82,28 @@ pub enum SplitState {
 }
 
 impl WmStatus {
+    /// Toggle between tiling and floating
     pub fn status_change(&mut self) {
         match self {
-            WmStatus::Tile => *self = WmStatus::Stack,
+            WmStatus::Tile | WmStatus::Master | WmStatus::Scroll => *self = WmStatus::Stack,
             WmStatus::Stack => *self = WmStatus::Tile,
         }
     }
+
+    /// Go through every mode
+    pub fn cycle(&mut self) {
+        *self = match self {
+            WmStatus::Tile => WmStatus::Master,
+            WmStatus::Master => WmStatus::Scroll,
+            WmStatus::Scroll => WmStatus::Stack,
+            WmStatus::Stack => WmStatus::Tile,
+        }
+    }
+
+    /// The windows are laid out by flyja
+    pub fn is_tiled(self) -> bool {
+        self != WmStatus::Stack
+    }
 }
 
 pub struct FlyJa<BackendData: Backend + 'static> {
@@ -88,162 +118,26 @@ pub struct FlyJa<BackendData: Backend + 'static> {
     // State
     pub compositor_state: CompositorState,
     pub xdg_shell_state: XdgShellState,
+    pub layer_shell_state: WlrLayerShellState,
+    pub xdg_decoration_state: XdgDecorationState,
     pub xdg_activation_state: XdgActivationState,
     pub shm_state: ShmState,
     pub output_manager_state: OutputManagerState,
     pub seat_state: SeatState<FlyJa<BackendData>>,
     pub pointer: PointerHandle<FlyJa<BackendData>>,
+    pub cursor: Cursor,
+    /// the image the client under the pointer asked for
+    pub cursor_status: CursorImageStatus,
     pub data_device_state: DataDeviceState,
 
     pub seat: Seat<Self>,
     pub seat_name: String,
 
-    pub reseize_state: PeddingResize,
-    pub wmstatus: WmStatus,
-    pub splitstate: SplitState,
-    pub window_remove_state: WindowRemoved,
-}
-
-impl<BackendData: Backend + 'static> FlyJa<BackendData> {
-    pub fn find_to_resize_v_down(
-        &self,
-        (start_x, start_y): (i32, i32),
-        (end_x, end_y): (i32, i32),
-    ) -> Vec<((i32, i32), WindowElement)> {
-        let mut output = Vec::new();
-        let Some(window) = self.space.elements().find(|w| {
-            let Some(Point { x, y, .. }) = self.space.element_location(w) else {
-                return false;
-            };
-            let Size { w, h, .. } = w.geometry().size;
-            (x - start_x).abs() < 5 && (y + h - start_y).abs() < 5 && x + w <= end_x + 5
-        }) else {
-            return output;
-        };
-        let Some(Point { x, y, .. }) = self.space.element_location(window) else {
-            return output;
-        };
-        let Size { w, .. } = window.geometry().size;
-        output.push(((x, y), window.clone()));
-        if (x + w - end_x).abs() < 5 {
-            return output;
-        }
-
-        let mut others = self.find_to_resize_v_down((start_x + w, start_y), (end_x, end_y));
-
-        if others.is_empty() {
-            return Vec::new();
-        }
-
-        output.append(&mut others);
-
-        output
-    }
-
-    pub fn find_to_resize_v_top(
-        &self,
-        (start_x, start_y): (i32, i32),
-        (end_x, end_y): (i32, i32),
-    ) -> Vec<((i32, i32), WindowElement)> {
-        let mut output = Vec::new();
-        let Some(window) = self.space.elements().find(|w| {
-            let Some(Point { x, y, .. }) = self.space.element_location(w) else {
-                return false;
-            };
-            let Size { w, .. } = w.geometry().size;
-            (x - start_x).abs() < 5 && (y - end_y).abs() < 5 && x + w <= end_x + 5
-        }) else {
-            return output;
-        };
-        let Some(Point { x, .. }) = self.space.element_location(window) else {
-            return output;
-        };
-        let Size { w, .. } = window.geometry().size;
-        output.push(((start_x, start_y), window.clone()));
-        if (x + w - end_x).abs() < 5 {
-            return output;
-        }
-
-        let mut others = self.find_to_resize_v_top((start_x + w, start_y), (end_x, end_y));
-
-        if others.is_empty() {
-            return Vec::new();
-        }
-
-        output.append(&mut others);
-
-        output
-    }
-
-    pub fn find_to_resize_h_right(
-        &self,
-        (start_x, start_y): (i32, i32),
-        (end_x, end_y): (i32, i32),
-    ) -> Vec<((i32, i32), WindowElement)> {
-        let mut output = Vec::new();
-        let Some(window) = self.space.elements().find(|window| {
-            let Some(Point { x, y, .. }) = self.space.element_location(window) else {
-                return false;
-            };
-            let Size { w, h, .. } = window.geometry().size;
-            (x + w - start_x).abs() < 5 && (y - start_y).abs() < 5 && y + h <= end_y + 5
-        }) else {
-            return output;
-        };
-        let Some(Point { y, x, .. }) = self.space.element_location(window) else {
-            return output;
-        };
-        let Size { h, .. } = window.geometry().size;
-        output.push(((x, start_y), window.clone()));
-        if (y + h - end_y).abs() < 5 {
-            return output;
-        }
-
-        let mut others = self.find_to_resize_h_right((start_x, start_y + h), (end_x, end_y));
-
-        if others.is_empty() {
-            return Vec::new();
-        }
-
-        output.append(&mut others);
+    pub config: Config,
+    pub ipc_state: IpcState,
 
-        output
-    }
-
-    pub fn find_to_resize_h_left(
-        &self,
-        (start_x, start_y): (i32, i32),
-        (end_x, end_y): (i32, i32),
-    ) -> Vec<((i32, i32), WindowElement)> {
-        let mut output = Vec::new();
-        let Some(window) = self.space.elements().find(|window| {
-            let Some(Point { x, y, .. }) = self.space.element_location(window) else {
-                return false;
-            };
-            let Size { h, .. } = window.geometry().size;
-            (x - end_x).abs() < 5 && (y - start_y).abs() < 5 && y + h <= end_y + 5
-        }) else {
-            return output;
-        };
-        let Some(Point { y, .. }) = self.space.element_location(window) else {
-            return output;
-        };
-        let Size { h, .. } = window.geometry().size;
-        output.push(((start_x, start_y), window.clone()));
-        if (y + h - end_y).abs() < 5 {
-            return output;
-        }
-
-        let mut others = self.find_to_resize_h_left((start_x, start_y + h), (end_x, end_y));
-
-        if others.is_empty() {
-            return Vec::new();
-        }
-
-        output.append(&mut others);
-
-        output
-    }
+    pub workspaces: Vec<Workspace>,
+    pub current_workspace: usize,
 }
 
 impl<BackendData: Backend + 'static> FlyJa<BackendData> {
@@ -258,6 +152,8 @@ impl<BackendData: Backend + 'static> FlyJa<BackendData> {
 
         let compositor_state = CompositorState::new::<Self>(&dh);
         let xdg_shell_state = XdgShellState::new::<Self>(&dh);
+        let layer_shell_state = WlrLayerShellState::new::<Self>(&dh);
+        let xdg_decoration_state = XdgDecorationState::new::<Self>(&dh);
         let shm_state = ShmState::new::<Self>(&dh, Vec::new());
         let output_manager_state = OutputManagerState::new_with_xdg_output::<Self>(&dh);
         let mut seat_state = SeatState::new();
@@ -265,7 +161,14 @@ impl<BackendData: Backend + 'static> FlyJa<BackendData> {
 
         let mut seat: Seat<Self> = seat_state.new_wl_seat(&dh, "winit");
 
-        seat.add_keyboard(Default::default(), 200, 200).unwrap();
+        let config = Config::load();
+
+        seat.add_keyboard(
+            config.input.xkb_config(),
+            config.input.repeat_delay,
+            config.input.repeat_rate,
+        )
+        .unwrap();
 
         let pointer = seat.add_pointer();
 
@@ -273,6 +176,10 @@ impl<BackendData: Backend + 'static> FlyJa<BackendData> {
 
         let socket_name = Self::init_wayland_listener(display, event_loop);
 
+        init_config_watcher(&event_loop.handle());
+
+        let ipc_state = IpcState::new(init_ipc_listener(&event_loop.handle(), &socket_name));
+
         let loop_signal = event_loop.get_signal();
 
         let seat_name = backend_data.seat_name();
@@ -287,6 +194,8 @@ impl<BackendData: Backend + 'static> FlyJa<BackendData> {
 
             compositor_state,
             xdg_shell_state,
+            layer_shell_state,
+            xdg_decoration_state,
             xdg_activation_state: XdgActivationState::new::<Self>(&dh),
             shm_state,
             output_manager_state,
@@ -295,12 +204,16 @@ impl<BackendData: Backend + 'static> FlyJa<BackendData> {
             data_device_state,
             seat,
             pointer,
+            cursor: Cursor::load(),
+            cursor_status: CursorImageStatus::Default,
             seat_name,
 
-            reseize_state: PeddingResize::Stop,
-            wmstatus: WmStatus::Tile,
-            splitstate: SplitState::H,
-            window_remove_state: WindowRemoved::NoState,
+            workspaces: (0..WORKSPACE_COUNT)
+                .map(|_| Workspace::new(&config.layout))
+                .collect(),
+            config,
+            ipc_state,
+            current_workspace: 0,
         }
     }
 
@@ -344,321 +257,307 @@ impl<BackendData: Backend + 'static> FlyJa<BackendData> {
         socket_name
     }
 
-    pub fn set_split_state(&mut self, state: SplitState) {
-        self.splitstate = state;
-        let Some(window) = self.space.elements().find(|w| {
-            w.toplevel()
-                .current_state()
-                .states
-                .contains(xdg_toplevel::State::Activated)
-        }) else {
+    /// Run a command line as a client of this compositor
+    pub fn spawn(&self, cmd: &str) {
+        let mut args = cmd.split_whitespace();
+        let Some(program) = args.next() else {
             return;
         };
-        let surface = window.toplevel();
-
-        let xdg_state = match state {
-            SplitState::H => xdg_toplevel::State::TiledRight,
-            SplitState::V => xdg_toplevel::State::TiledBottom,
-        };
+        let mut command = std::process::Command::new(program);
+        command
+            .args(args)
+            .env("WAYLAND_DISPLAY", self.socket_name.clone());
+        if let Some(path) = &self.ipc_state.socket_path {
+            command.env(SOCKET_ENV, path);
+        }
+        if let Err(e) = command.spawn() {
+            tracing::error!(cmd, err = %e, "Failed to start program");
+        }
+    }
 
-        surface.with_pending_state(|state| {
-            state.states.set(xdg_state);
-        });
-        surface.send_pending_configure();
+    pub fn run_startup(&self) {
+        for cmd in self.config.startup.iter() {
+            self.spawn(cmd);
+        }
     }
 
-    fn get_surface_size_and_point(&mut self) -> Option<(WlSurface, i32, i32, i32, i32)> {
-        let Some(window) = self.space.elements().find(|w| {
-            w.toplevel()
-                .current_state()
-                .states
-                .contains(xdg_toplevel::State::Activated)
-        }) else {
-            return None;
-        };
+    pub fn set_split_state(&mut self, state: SplitState) {
+        self.workspace_mut().splitstate = state;
+    }
 
-        let geometry = window.geometry();
+    /// Ask the windows to close and stop the event loop, the backend gives
+    /// the session back when it is dropped
+    pub fn quit(&mut self) {
+        for window in self
+            .workspaces
+            .iter()
+            .flat_map(|workspace| workspace.windows())
+        {
+            window.toplevel().send_close();
+        }
+        self.loop_signal.stop();
+    }
 
-        let Some(Point { x, y, .. }) = self.space.element_location(window) else {
-            return None;
-        };
+    pub fn change_wm_status(&mut self) {
+        self.workspace_mut().wmstatus.status_change();
+        self.wm_status_changed();
+    }
 
-        let (x, y, width, height) = match self.splitstate {
-            SplitState::H => {
-                let x = x + geometry.size.w / 2;
+    pub fn cycle_wm_status(&mut self) {
+        self.workspace_mut().wmstatus.cycle();
+        self.wm_status_changed();
+    }
 
-                let width = geometry.size.w / 2;
-                let height = geometry.size.h;
-                (x, y, width, height)
+    /// Lay the windows out in the mode of the workspace, the floating ones
+    /// are not tiled anymore
+    pub fn wm_status_changed(&mut self) {
+        if !self.workspace().wmstatus.is_tiled() {
+            // the tabs left out of the space float again from the corner
+            // of the layout area
+            let location = self.layout_area().map(|area| area.loc).unwrap_or_default();
+            for window in self.workspace().layout.windows() {
+                if !self.space.elements().any(|w| *w == window) {
+                    self.space.map_element(window, location, false);
+                }
             }
-            SplitState::V => {
-                let y = y + geometry.size.h / 2;
-
-                let width = geometry.size.w;
-                let height = geometry.size.h / 2;
-                (x, y, width, height)
+            for window in self.space.elements() {
+                window.set_tiled_edges(ResizeEdge::empty());
+                if window.initial_configure_sent() {
+                    window.toplevel().send_pending_configure();
+                }
             }
-        };
-
-        let newwindow = window.set_resize_size((width, height));
-        let surface = newwindow.toplevel();
-
-        surface.with_pending_state(|state| {
-            state.states.set(xdg_toplevel::State::Resizing);
-            state.size = Some((width, height).into());
-        });
-        surface.send_pending_configure();
-
-        newwindow.remap_element(&mut self.space);
+        }
+        self.apply_layout();
+    }
 
-        Some((surface.wl_surface().clone(), x, y, width, height))
+    /// Change the gaps until the config is loaded again, they are never
+    /// below zero
+    pub fn set_gaps(&mut self, inner: Option<i32>, outer: Option<i32>) {
+        let gaps = &mut self.config.layout.gaps;
+        if let Some(inner) = inner {
+            gaps.inner = inner.max(0);
+        }
+        if let Some(outer) = outer {
+            gaps.outer = outer.max(0);
+        }
+        self.apply_layout();
     }
 
-    pub fn surface_under_pointer(
+    /// The surface under the position, with its location
+    pub fn surface_under(
         &self,
-        pointer: &PointerHandle<Self>,
+        pos: Point<f64, Logical>,
     ) -> Option<(WlSurface, Point<i32, Logical>)> {
-        let pos = pointer.current_location();
-        self.space
-            .element_under(pos)
-            .and_then(|(window, location)| {
-                window
-                    .surface_under(pos - location.to_f64(), WindowSurfaceType::ALL)
-                    .map(|(s, p)| (s, p + location))
+        if let Some(window) = self.fullscreen_window_under(pos) {
+            let location = self.space.element_location(&window)?;
+            return window
+                .surface_under(pos - location.to_f64(), WindowSurfaceType::ALL)
+                .map(|(s, p)| (s, p + location));
+        }
+        self.layer_surface_under(&[Layer::Overlay, Layer::Top], pos)
+            .or_else(|| {
+                self.space
+                    .element_under(pos)
+                    .and_then(|(window, location)| {
+                        window
+                            .surface_under(pos - location.to_f64(), WindowSurfaceType::ALL)
+                            .map(|(s, p)| (s, p + location))
+                    })
             })
+            .or_else(|| self.layer_surface_under(&[Layer::Bottom, Layer::Background], pos))
+    }
+
+    /// The output the workspaces are shown on, the first one mapped
+    pub fn primary_output(&self) -> Option<&Output> {
+        self.space.outputs().next()
+    }
+
+    /// The area the tile tree is laid into, what the exclusive zones of
+    /// the layer surfaces leave of the primary output. It does not follow
+    /// the pointer, a relayout keeps the workspace on its output
+    pub fn layout_area(&self) -> Option<Rectangle<i32, Logical>> {
+        self.usable_area(self.primary_output()?)
     }
 
-    fn handle_one_element(&mut self, surface: &WlSurface) {
-        self.reseize_state = PeddingResize::ResizeFinished(surface.clone());
-        let Some(window) = self
+    /// The usable area of the output under the pointer
+    pub fn pointer_area(&self) -> Option<Rectangle<i32, Logical>> {
+        let output = self
             .space
-            .elements()
-            .find(|w| w.toplevel().wl_surface() == surface)
-        else {
-            return;
-        };
-        let prosize = {
-            let Some(output) = self
-                .space
-                .output_under(self.pointer.current_location())
-                .next()
-            else {
-                return;
-            };
-            let Some(geo) = self.space.output_geometry(output) else {
-                return;
-            };
-            geo.size
-        };
-        let newwindow = window.set_resize_size((prosize.w, prosize.h));
-        let surface_top = newwindow.toplevel();
-        surface_top.with_pending_state(|state| {
-            state.states.set(xdg_toplevel::State::Resizing);
-            let size = prosize;
-            state.size = Some(size);
+            .output_under(self.pointer.current_location())
+            .next()
+            .or_else(|| self.primary_output())?;
+        self.usable_area(output)
+    }
+
+    /// What the exclusive zones of the layer surfaces leave of the output
+    pub fn usable_area(&self, output: &Output) -> Option<Rectangle<i32, Logical>> {
+        let output_geometry = self.space.output_geometry(output)?;
+        let mut area = layer_map_for_output(output).non_exclusive_zone();
+        area.loc += output_geometry.loc;
+        Some(area)
+    }
+
+    /// The topmost fullscreen window of the output under the position, it
+    /// takes the input over the layer surfaces and the other windows
+    pub fn fullscreen_window_under(&self, pos: Point<f64, Logical>) -> Option<WindowElement> {
+        let output = self.space.output_under(pos).next()?;
+        fullscreen_window(&self.space, output)
+    }
+
+    /// Send the frame callbacks to the windows, the layer surfaces and the
+    /// cursor surface shown on the output, the windows of the hidden
+    /// workspaces are left waiting for theirs
+    pub fn send_frames(&self, output: &Output) {
+        let time = self.start_time.elapsed();
+        self.space.elements_for_output(output).for_each(|window| {
+            window.send_frame(output, time, Some(Duration::ZERO), |_, _| {
+                Some(output.clone())
+            })
         });
-        surface_top.send_configure();
-        newwindow.remap_element(&mut self.space);
+        for layer in layer_map_for_output(output).layers() {
+            layer.send_frame(output, time, Some(Duration::ZERO), |_, _| {
+                Some(output.clone())
+            });
+        }
+        if let CursorImageStatus::Surface(ref surface) = self.cursor_status {
+            if surface.alive() {
+                send_frames_surface_tree(surface, output, time, Some(Duration::ZERO), |_, _| {
+                    Some(output.clone())
+                });
+            }
+        }
     }
 
-    fn handle_split_element(&mut self, surface: &WlSurface) {
-        let Some((surface_before, x, y, width, height)) = self.get_surface_size_and_point() else {
-            self.reseize_state = PeddingResize::ResizeFinished(surface.clone());
-            return;
-        };
-        let Some(window) = self
+    /// Place every window of the tile tree and the fullscreen and maximized
+    /// windows, and send the new sizes to the clients
+    pub fn apply_layout(&mut self) {
+        if self.workspace().wmstatus.is_tiled() {
+            if let Some(arrangement) = self.arrangement() {
+                let (tiles, hidden) = (&arrangement.tiles, &arrangement.hidden);
+                for (window, geometry) in tiles.iter().chain(hidden) {
+                    // they keep their tile to go back to
+                    if window.is_fullscreen() || window.is_maximized() {
+                        continue;
+                    }
+                    window.set_tiled_edges(touching_edges(*geometry, tiles, arrangement.gap));
+                    if hidden.iter().any(|(w, _)| w == window) {
+                        self.hide_window(window, *geometry);
+                    } else {
+                        self.place_window(window.clone(), *geometry);
+                    }
+                }
+            }
+            self.raise_floating();
+        }
+        let placed: Vec<WindowElement> = self
             .space
             .elements()
-            .find(|w| w.toplevel().wl_surface() == surface)
+            .filter(|window| window.is_fullscreen() || window.is_maximized())
             .cloned()
-        else {
-            return;
-        };
-        let newwindow = window.set_resize_size((width, height));
-        let surface = newwindow.toplevel();
-        surface.with_pending_state(|state| {
-            state.states.set(xdg_toplevel::State::Resizing);
-            state.size = Some((width, height).into());
-        });
-        surface.send_pending_configure();
-        //newwindow.remap_element(&mut self.space);
-        self.reseize_state =
-            PeddingResize::ResizeTwoWindowFinished((surface_before, surface.wl_surface().clone()));
+            .collect();
+        for window in placed {
+            if let Some(geometry) = self.placement_geometry(&window) {
+                self.place_window(window, geometry);
+            }
+        }
+    }
 
-        self.space.map_element(newwindow, (x, y), true);
+    /// The window is laid out by flyja rather than floating, on whichever
+    /// workspace it is
+    pub fn is_tiled_window(&self, window: &WindowElement) -> bool {
+        self.workspace_of(window)
+            .map_or(false, |index| self.workspaces[index].wmstatus.is_tiled())
+            && !window.is_floating()
     }
 
-    pub fn handle_resize_tile_window_changing(&mut self) {
-        let PeddingResize::Resizing(ref surface) = self.reseize_state else {
-            return;
-        };
-        let count = self.space.elements().count();
-        if count == 1 {
-            self.handle_one_element(&surface.clone());
-        } else {
-            self.handle_split_element(&surface.clone());
+    /// Keep the floating windows above the tiles, in their order
+    pub fn raise_floating(&mut self) {
+        let floating: Vec<WindowElement> = self
+            .space
+            .elements()
+            .filter(|window| window.is_floating())
+            .cloned()
+            .collect();
+        for window in floating {
+            self.space.raise_element(&window, false);
         }
     }
 
-    // FIXME: I do not know when I can get the geometry
-    #[allow(unused)]
-    pub fn handle_place_stack_to_center(&mut self) {
-        let PeddingResize::ResizeFinished(ref surface) = self.reseize_state else {
-            return;
-        };
-
-        let Some(output) = self
-            .space
-            .output_under(self.pointer.current_location())
-            .next()
-        else {
-            return;
-        };
-        let Some(geo) = self.space.output_geometry(output) else {
-            return;
-        };
+    /// How the current workspace is laid into the layout area
+    pub fn arrangement(&self) -> Option<Arrangement> {
+        let area = self.layout_area()?;
+        Some(self.workspace().arrange(area, &self.config.layout.gaps))
+    }
 
-        let Some(window) = self
-            .space
-            .elements()
-            .find(|w| w.toplevel().wl_surface() == surface)
-        else {
-            return;
-        };
-        let gerwindow = window.geometry();
-        let pos_x = geo.size.w / 2 - gerwindow.size.w / 2;
-        let pox_y = geo.size.h / 2 - gerwindow.size.h / 2;
-        self.space
-            .map_element(window.clone(), (pos_x, pox_y), false);
-        self.reseize_state = PeddingResize::Stop;
+    /// The tile of the window on its workspace, also while the workspace
+    /// is hidden
+    pub fn tile_geometry(&self, window: &WindowElement) -> Option<Rectangle<i32, Logical>> {
+        let area = self.layout_area()?;
+        let arrangement =
+            self.workspaces[self.workspace_of(window)?].arrange(area, &self.config.layout.gaps);
+        arrangement
+            .tiles
+            .into_iter()
+            .chain(arrangement.hidden)
+            .find(|(tile, _)| tile == window)
+            .map(|(_, geometry)| geometry)
     }
 
-    pub fn handle_resize_tile_split_window_finished(&mut self) {
-        let PeddingResize::ResizeTwoWindowFinished((ref surfacea, ref surfaceb)) =
-            self.reseize_state
-        else {
-            return;
-        };
-        if self.wmstatus != WmStatus::Tile {
-            return;
-        }
-        let Some(windowa) = self
-            .space
-            .elements()
-            .find(|w| w.toplevel().wl_surface() == surfacea)
-        else {
-            return;
-        };
-        let Some(windowb) = self
-            .space
-            .elements()
-            .find(|w| w.toplevel().wl_surface() == surfaceb)
-        else {
-            return;
-        };
-        let surfacea = windowa.toplevel();
-        surfacea.with_pending_state(|state| {
-            state.states.unset(xdg_toplevel::State::Resizing);
-        });
+    /// The tabs of the containers of the current workspace
+    pub fn tabs(&self) -> Vec<Tab> {
+        self.arrangement()
+            .map(|arrangement| arrangement.tabs)
+            .unwrap_or_default()
+    }
 
-        let surfaceb = windowb.toplevel();
-        surfaceb.with_pending_state(|state| {
-            state.states.unset(xdg_toplevel::State::Resizing);
-        });
-        surfaceb.send_configure();
-        self.reseize_state = PeddingResize::Stop;
+    /// The window of the tab under the position
+    pub fn tab_under(&self, pos: Point<f64, Logical>) -> Option<WindowElement> {
+        self.tabs()
+            .into_iter()
+            .find(|tab| tab.geometry.to_f64().contains(pos))
+            .map(|tab| tab.window)
     }
 
-    pub fn handle_resize_tile_window_finished(&mut self) {
-        let PeddingResize::ResizeFinished(ref surface) = self.reseize_state else {
-            return;
-        };
-        if self.wmstatus != WmStatus::Tile {
-            return;
-        }
-        let Some(window) = self
-            .space
-            .elements()
-            .find(|w| w.toplevel().wl_surface() == surface)
-        else {
-            return;
-        };
+    /// Send the window of a tab not selected the size of the tab and take
+    /// it out of the space, it is neither drawn nor sent frame callbacks
+    /// until its tab is selected
+    pub fn hide_window(&mut self, window: &WindowElement, geometry: Rectangle<i32, Logical>) {
+        window.set_activated(false);
         let surface = window.toplevel();
         surface.with_pending_state(|state| {
-            state.states.unset(xdg_toplevel::State::Resizing);
+            state.size = Some(window.content_size(geometry.size));
         });
-        surface.send_configure();
-        self.reseize_state = PeddingResize::Stop;
+        if window.initial_configure_sent() {
+            surface.send_pending_configure();
+        }
+        self.space.unmap_elem(window);
     }
 
-    pub fn handle_window_removed_mul(&mut self) {
-        let WindowRemoved::Region { pos_start, pos_end } = self.window_remove_state else {
-            return;
-        };
-        let (elements_and_poss, state) = 'surface: {
-            let surfacesa = self.find_to_resize_v_down(pos_start, pos_end);
-            if !surfacesa.is_empty() {
-                break 'surface (surfacesa, 0);
-            }
-            let surfaceb = self.find_to_resize_v_top(pos_start, pos_end);
-            if !surfaceb.is_empty() {
-                break 'surface (surfaceb, 1);
-            }
-            let surfacec = self.find_to_resize_h_left(pos_start, pos_end);
-            if !surfacec.is_empty() {
-                break 'surface (surfacec, 2);
-            }
-            (self.find_to_resize_h_right(pos_start, pos_end), 3)
-        };
-        for ((start_x, start_y), window) in elements_and_poss.iter() {
-            // FIXME:
-            let Size { w, h, .. } = window.geometry().size;
-            let height_add = pos_end.1 - pos_start.1;
-            let width_add = pos_end.0 - pos_start.0;
-            let surface = window.toplevel();
-            let size = match state {
-                0 | 1 => (w, h + height_add).into(),
-                2 | 3 => (w + width_add, h).into(),
-                _ => unreachable!(),
-            };
-            surface.with_pending_state(|state| {
-                state.states.set(xdg_toplevel::State::Resizing);
-                state.size = Some(size);
-            });
+    /// Map the window at the geometry and send it the new size. Windows
+    /// still waiting for their initial configure only get the pending
+    /// state, it is sent with the initial configure
+    pub fn place_window(&mut self, window: WindowElement, geometry: Rectangle<i32, Logical>) {
+        let surface = window.toplevel();
+        surface.with_pending_state(|state| {
+            state.size = Some(window.content_size(geometry.size));
+        });
+        if window.initial_configure_sent() {
             surface.send_pending_configure();
-            let newwindow = window.set_resize_size((size.w, size.h));
-            self.space
-                .map_element(newwindow, (*start_x, *start_y), true);
         }
-        let surfaces: Vec<WlSurface> = elements_and_poss
-            .iter()
-            .map(|e| e.1.toplevel().wl_surface().clone())
-            .collect();
-        self.window_remove_state = WindowRemoved::PeddingMutiResizeFinished(surfaces);
+        self.space.map_element(window, geometry.loc, false);
     }
 
-    pub fn handle_window_mul_removed_finished(&mut self) {
-        let WindowRemoved::PeddingMutiResizeFinished(ref surfaces) = self.window_remove_state
-        else {
-            return;
-        };
-        for surface in surfaces {
-            let Some(window) = self
-                .space
-                .elements()
-                .find(|w| w.toplevel().wl_surface() == surface)
-                .cloned()
-            else {
-                return;
-            };
-
-            let surface = window.toplevel();
-            surface.with_pending_state(|state| {
-                state.states.unset(xdg_toplevel::State::Resizing);
-            });
-            surface.send_configure();
+    pub fn handle_window_removed(&mut self, window: &WindowElement) {
+        let focused = self.focused_window().as_ref() == Some(window);
+        self.workspaces
+            .iter_mut()
+            .for_each(|workspace| workspace.remove(window));
+        self.space.unmap_elem(window);
+        self.apply_layout();
+        if focused {
+            self.focus_last_window();
         }
-        self.window_remove_state = WindowRemoved::NoState;
+        self.ipc_event(Event::WindowClosed { id: window.id() });
     }
 
     #[allow(unused)]
@@ -753,7 +652,7 @@ impl<BackendData: Backend> XdgActivationHandler for FlyJa<BackendData> {
                 .find(|window| window.wl_surface().map(|s| s == surface).unwrap_or(false))
                 .cloned();
             if let Some(window) = w {
-                self.space.raise_element(&window, true);
+                self.focus_window(&window, SERIAL_COUNTER.next_serial());
             }
         } else {
             // Discard the request
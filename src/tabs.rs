@@ -0,0 +1,315 @@
+use std::collections::HashMap;
+
+use smithay::{
+    backend::{
+        allocator::Fourcc,
+        renderer::{
+            element::{
+                texture::{TextureBuffer, TextureRenderElement},
+                Kind,
+            },
+            ImportAll, ImportMem, Renderer, Texture,
+        },
+    },
+    render_elements,
+    utils::{Logical, Physical, Point, Rectangle, Scale, Transform},
+};
+
+use crate::{
+    config::{BorderConfig, Color},
+    layout::Tab,
+};
+
+/// The glyphs are drawn at twice the size of the font
+const GLYPH_SCALE: i32 = 2;
+const GLYPH_WIDTH: i32 = 5 * GLYPH_SCALE;
+const GLYPH_HEIGHT: i32 = 7 * GLYPH_SCALE;
+const GLYPH_ADVANCE: i32 = GLYPH_WIDTH + GLYPH_SCALE;
+const TEXT_PADDING: i32 = 6;
+const TEXT_COLOR: [u8; 4] = [255, 255, 255, 255];
+
+/// A 5x7 font for the printable ascii characters, a byte per column with
+/// the top row in the lowest bit
+const FONT: [[u8; 5]; 95] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00], // ' '
+    [0x00, 0x00, 0x5f, 0x00, 0x00], // !
+    [0x00, 0x07, 0x00, 0x07, 0x00], // "
+    [0x14, 0x7f, 0x14, 0x7f, 0x14], // #
+    [0x24, 0x2a, 0x7f, 0x2a, 0x12], // $
+    [0x23, 0x13, 0x08, 0x64, 0x62], // %
+    [0x36, 0x49, 0x55, 0x22, 0x50], // &
+    [0x00, 0x05, 0x03, 0x00, 0x00], // '
+    [0x00, 0x1c, 0x22, 0x41, 0x00], // (
+    [0x00, 0x41, 0x22, 0x1c, 0x00], // )
+    [0x08, 0x2a, 0x1c, 0x2a, 0x08], // *
+    [0x08, 0x08, 0x3e, 0x08, 0x08], // +
+    [0x00, 0x50, 0x30, 0x00, 0x00], // ,
+    [0x08, 0x08, 0x08, 0x08, 0x08], // -
+    [0x00, 0x60, 0x60, 0x00, 0x00], // .
+    [0x20, 0x10, 0x08, 0x04, 0x02], // /
+    [0x3e, 0x51, 0x49, 0x45, 0x3e], // 0
+    [0x00, 0x42, 0x7f, 0x40, 0x00], // 1
+    [0x42, 0x61, 0x51, 0x49, 0x46], // 2
+    [0x21, 0x41, 0x45, 0x4b, 0x31], // 3
+    [0x18, 0x14, 0x12, 0x7f, 0x10], // 4
+    [0x27, 0x45, 0x45, 0x45, 0x39], // 5
+    [0x3c, 0x4a, 0x49, 0x49, 0x30], // 6
+    [0x01, 0x71, 0x09, 0x05, 0x03], // 7
+    [0x36, 0x49, 0x49, 0x49, 0x36], // 8
+    [0x06, 0x49, 0x49, 0x29, 0x1e], // 9
+    [0x00, 0x36, 0x36, 0x00, 0x00], // :
+    [0x00, 0x56, 0x36, 0x00, 0x00], // ;
+    [0x08, 0x14, 0x22, 0x41, 0x00], // <
+    [0x14, 0x14, 0x14, 0x14, 0x14], // =
+    [0x00, 0x41, 0x22, 0x14, 0x08], // >
+    [0x02, 0x01, 0x51, 0x09, 0x06], // ?
+    [0x32, 0x49, 0x79, 0x41, 0x3e], // @
+    [0x7e, 0x11, 0x11, 0x11, 0x7e], // A
+    [0x7f, 0x49, 0x49, 0x49, 0x36], // B
+    [0x3e, 0x41, 0x41, 0x41, 0x22], // C
+    [0x7f, 0x41, 0x41, 0x22, 0x1c], // D
+    [0x7f, 0x49, 0x49, 0x49, 0x41], // E
+    [0x7f, 0x09, 0x09, 0x09, 0x01], // F
+    [0x3e, 0x41, 0x49, 0x49, 0x7a], // G
+    [0x7f, 0x08, 0x08, 0x08, 0x7f], // H
+    [0x00, 0x41, 0x7f, 0x41, 0x00], // I
+    [0x20, 0x40, 0x41, 0x3f, 0x01], // J
+    [0x7f, 0x08, 0x14, 0x22, 0x41], // K
+    [0x7f, 0x40, 0x40, 0x40, 0x40], // L
+    [0x7f, 0x02, 0x0c, 0x02, 0x7f], // M
+    [0x7f, 0x04, 0x08, 0x10, 0x7f], // N
+    [0x3e, 0x41, 0x41, 0x41, 0x3e], // O
+    [0x7f, 0x09, 0x09, 0x09, 0x06], // P
+    [0x3e, 0x41, 0x51, 0x21, 0x5e], // Q
+    [0x7f, 0x09, 0x19, 0x29, 0x46], // R
+    [0x46, 0x49, 0x49, 0x49, 0x31], // S
+    [0x01, 0x01, 0x7f, 0x01, 0x01], // T
+    [0x3f, 0x40, 0x40, 0x40, 0x3f], // U
+    [0x1f, 0x20, 0x40, 0x20, 0x1f], // V
+    [0x3f, 0x40, 0x38, 0x40, 0x3f], // W
+    [0x63, 0x14, 0x08, 0x14, 0x63], // X
+    [0x07, 0x08, 0x70, 0x08, 0x07], // Y
+    [0x61, 0x51, 0x49, 0x45, 0x43], // Z
+    [0x00, 0x7f, 0x41, 0x41, 0x00], // [
+    [0x02, 0x04, 0x08, 0x10, 0x20], // \
+    [0x00, 0x41, 0x41, 0x7f, 0x00], // ]
+    [0x04, 0x02, 0x01, 0x02, 0x04], // ^
+    [0x40, 0x40, 0x40, 0x40, 0x40], // _
+    [0x00, 0x01, 0x02, 0x04, 0x00], // `
+    [0x20, 0x54, 0x54, 0x54, 0x78], // a
+    [0x7f, 0x48, 0x44, 0x44, 0x38], // b
+    [0x38, 0x44, 0x44, 0x44, 0x20], // c
+    [0x38, 0x44, 0x44, 0x48, 0x7f], // d
+    [0x38, 0x54, 0x54, 0x54, 0x18], // e
+    [0x08, 0x7e, 0x09, 0x01, 0x02], // f
+    [0x0c, 0x52, 0x52, 0x52, 0x3e], // g
+    [0x7f, 0x08, 0x04, 0x04, 0x78], // h
+    [0x00, 0x44, 0x7d, 0x40, 0x00], // i
+    [0x20, 0x40, 0x44, 0x3d, 0x00], // j
+    [0x7f, 0x10, 0x28, 0x44, 0x00], // k
+    [0x00, 0x41, 0x7f, 0x40, 0x00], // l
+    [0x7c, 0x04, 0x18, 0x04, 0x78], // m
+    [0x7c, 0x08, 0x04, 0x04, 0x78], // n
+    [0x38, 0x44, 0x44, 0x44, 0x38], // o
+    [0x7c, 0x14, 0x14, 0x14, 0x08], // p
+    [0x08, 0x14, 0x14, 0x18, 0x7c], // q
+    [0x7c, 0x08, 0x04, 0x04, 0x08], // r
+    [0x48, 0x54, 0x54, 0x54, 0x20], // s
+    [0x04, 0x3f, 0x44, 0x40, 0x20], // t
+    [0x3c, 0x40, 0x40, 0x20, 0x7c], // u
+    [0x1c, 0x20, 0x40, 0x20, 0x1c], // v
+    [0x3c, 0x40, 0x30, 0x40, 0x3c], // w
+    [0x44, 0x28, 0x10, 0x28, 0x44], // x
+    [0x0c, 0x50, 0x50, 0x50, 0x3c], // y
+    [0x44, 0x64, 0x54, 0x4c, 0x44], // z
+    [0x00, 0x08, 0x36, 0x41, 0x00], // {
+    [0x00, 0x00, 0x7f, 0x00, 0x00], // |
+    [0x00, 0x41, 0x36, 0x08, 0x00], // }
+    [0x08, 0x04, 0x08, 0x10, 0x08], // ~
+];
+
+/// What a tab looks like, a texture is kept for each
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct TabKey {
+    title: String,
+    /// logical size
+    size: (i32, i32),
+    /// buffer scale of the texture
+    scale: i32,
+    background: [u8; 4],
+}
+
+impl TabKey {
+    /// The size of the texture in pixels
+    fn buffer_size(&self) -> (i32, i32) {
+        (self.size.0 * self.scale, self.size.1 * self.scale)
+    }
+
+    /// The pixels of the tab, the title is cut where it does not fit
+    fn draw(&self) -> Vec<u8> {
+        let scale = self.scale;
+        let (width, height) = self.buffer_size();
+        let (glyph_scale, glyph_width, glyph_height) = (
+            GLYPH_SCALE * scale,
+            GLYPH_WIDTH * scale,
+            GLYPH_HEIGHT * scale,
+        );
+        let padding = TEXT_PADDING * scale;
+        let mut pixels = self.background.repeat((width * height) as usize);
+        let top = (height - glyph_height) / 2;
+        let mut left = padding;
+        for c in self.title.chars() {
+            if left + glyph_width > width - padding {
+                break;
+            }
+            let glyph = match c {
+                ' '..='~' => FONT[c as usize - ' ' as usize],
+                _ => FONT['?' as usize - ' ' as usize],
+            };
+            for x in 0..glyph_width {
+                for y in 0..glyph_height {
+                    let (px, py) = (left + x, top + y);
+                    if glyph[(x / glyph_scale) as usize] & (1 << (y / glyph_scale)) == 0
+                        || py < 0
+                        || py >= height
+                    {
+                        continue;
+                    }
+                    let index = ((py * width + px) * 4) as usize;
+                    pixels[index..index + 4].copy_from_slice(&TEXT_COLOR);
+                }
+            }
+            left += GLYPH_ADVANCE * scale;
+        }
+        pixels
+    }
+}
+
+fn color_bytes(color: Color, brightness: f32) -> [u8; 4] {
+    let [r, g, b, a] = color.0;
+    let byte = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+    [
+        byte(r * brightness),
+        byte(g * brightness),
+        byte(b * brightness),
+        byte(a),
+    ]
+}
+
+/// The tabs drawn above the tabbed and stacked containers, the textures
+/// are kept for the renderer they were uploaded with
+pub struct TabBars<T: Texture> {
+    textures: HashMap<TabKey, TextureBuffer<T>>,
+    shown: Vec<(Point<i32, Logical>, TabKey)>,
+}
+
+impl<T: Texture> Default for TabBars<T> {
+    fn default() -> Self {
+        Self {
+            textures: HashMap::new(),
+            shown: Vec::new(),
+        }
+    }
+}
+
+impl<T: Texture + Clone + 'static> TabBars<T> {
+    /// Take the tabs to draw on an output of the scale, the tab of the
+    /// focused window has the colour of the focused border and the tabs not
+    /// selected are darker
+    pub fn update<R>(&mut self, renderer: &mut R, tabs: &[Tab], border: &BorderConfig, scale: f64)
+    where
+        R: Renderer<TextureId = T> + ImportMem,
+    {
+        // drawn at the next integer scale and scaled down by the renderer
+        let scale = (scale.ceil() as i32).max(1);
+        self.shown.clear();
+        for tab in tabs {
+            let size = (tab.geometry.size.w, tab.geometry.size.h);
+            if size.0 <= 0 || size.1 <= 0 {
+                continue;
+            }
+            let background = match (tab.window.is_activated(), tab.selected) {
+                (true, _) => color_bytes(border.focused, 1.0),
+                (false, true) => color_bytes(border.unfocused, 1.0),
+                (false, false) => color_bytes(border.unfocused, 0.7),
+            };
+            let key = TabKey {
+                title: tab.window.title().unwrap_or_default(),
+                size,
+                scale,
+                background,
+            };
+            if !self.textures.contains_key(&key) {
+                match TextureBuffer::from_memory(
+                    renderer,
+                    &key.draw(),
+                    Fourcc::Abgr8888,
+                    key.buffer_size(),
+                    false,
+                    key.scale,
+                    Transform::Normal,
+                    None,
+                ) {
+                    Ok(texture) => {
+                        self.textures.insert(key.clone(), texture);
+                    }
+                    Err(err) => {
+                        tracing::warn!(?err, "Failed to upload a tab");
+                        continue;
+                    }
+                }
+            }
+            self.shown.push((tab.geometry.loc, key));
+        }
+        // the textures of the outputs of other scales are kept for them
+        let shown = &self.shown;
+        self.textures
+            .retain(|key, _| key.scale != scale || shown.iter().any(|(_, shown)| shown == key));
+    }
+
+    /// The tabs on the output
+    pub fn render_elements<R>(
+        &self,
+        output_geometry: Rectangle<i32, Logical>,
+        scale: Scale<f64>,
+    ) -> Vec<TabRenderElement<R>>
+    where
+        R: Renderer<TextureId = T> + ImportAll,
+    {
+        self.shown
+            .iter()
+            .filter(|(location, key)| {
+                output_geometry.overlaps(Rectangle::from_loc_and_size(*location, key.size))
+            })
+            .filter_map(|(location, key)| {
+                let texture = self.textures.get(key)?;
+                let location: Point<i32, Physical> =
+                    (*location - output_geometry.loc).to_physical_precise_round(scale);
+                Some(TabRenderElement::from(
+                    TextureRenderElement::from_texture_buffer(
+                        location.to_f64(),
+                        texture,
+                        None,
+                        None,
+                        None,
+                        Kind::Unspecified,
+                    ),
+                ))
+            })
+            .collect()
+    }
+}
+
+render_elements!(
+    pub TabRenderElement<R> where R: ImportAll;
+    Texture=TextureRenderElement<<R as Renderer>::TextureId>,
+);
+
+impl<R: Renderer> std::fmt::Debug for TabRenderElement<R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Texture(arg0) => f.debug_tuple("Texture").field(arg0).finish(),
+            Self::_GenericCatcher(arg0) => f.debug_tuple("_GenericCatcher").field(arg0).finish(),
+        }
+    }
+}
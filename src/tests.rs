@@ -0,0 +1,175 @@
+//! In process tests: the compositor runs on the headless backend and
+//! scripted wayland clients are connected to it over a socketpair. Both
+//! sides are driven from the test thread, see [`TestCompositor::roundtrip`].
+
+mod client;
+mod focus;
+mod tile;
+
+use std::{os::unix::net::UnixStream, path::PathBuf, sync::Arc, sync::Once, time::Duration};
+
+use smithay::{
+    desktop::space::SpaceElement,
+    reexports::{
+        calloop::EventLoop,
+        wayland_server::{Display, Resource},
+    },
+    utils::{Logical, Rectangle},
+};
+
+use crate::{
+    headless::{init_headless, HeadlessData},
+    shell::WindowElement,
+    state::ClientState,
+    CalloopData, FlyJa,
+};
+
+pub use client::TestClient;
+
+/// How many times both sides are dispatched in a roundtrip, enough for a
+/// configure to be acked and the new buffer to be committed
+const ROUNDTRIP_ITERATIONS: usize = 8;
+
+/// The sockets and the config of the compositor are kept out of the ones
+/// of the session running the tests
+fn init_test_env() {
+    static INIT: Once = Once::new();
+    INIT.call_once(|| {
+        let dir = std::env::temp_dir().join(format!("flyja-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::env::set_var("XDG_RUNTIME_DIR", &dir);
+        std::env::set_var("XDG_CONFIG_HOME", &dir);
+    });
+}
+
+pub fn test_runtime_dir() -> PathBuf {
+    init_test_env();
+    std::env::var_os("XDG_RUNTIME_DIR").unwrap().into()
+}
+
+pub struct TestCompositor {
+    event_loop: EventLoop<'static, CalloopData<HeadlessData>>,
+    pub data: CalloopData<HeadlessData>,
+}
+
+impl TestCompositor {
+    /// A compositor with one output of the size
+    pub fn new(width: i32, height: i32) -> Self {
+        init_test_env();
+        let mut event_loop = EventLoop::try_new().unwrap();
+        let mut display = Display::new().unwrap();
+        let state = FlyJa::init(HeadlessData, &mut event_loop, &mut display);
+        let mut data = CalloopData { state, display };
+        init_headless(&mut event_loop, &mut data, &[(width, height).into()]).unwrap();
+        Self { event_loop, data }
+    }
+
+    pub fn state(&self) -> &FlyJa<HeadlessData> {
+        &self.data.state
+    }
+
+    pub fn state_mut(&mut self) -> &mut FlyJa<HeadlessData> {
+        &mut self.data.state
+    }
+
+    pub fn output_geometry(&self) -> Rectangle<i32, Logical> {
+        let space = &self.state().space;
+        space
+            .output_geometry(space.outputs().next().unwrap())
+            .unwrap()
+    }
+
+    pub fn connect(&mut self) -> TestClient {
+        let (server, client) = UnixStream::pair().unwrap();
+        let id = self
+            .data
+            .display
+            .handle()
+            .insert_client(server, Arc::new(ClientState::default()))
+            .unwrap()
+            .id();
+        let mut client = TestClient::new(client, id);
+        self.roundtrip(&mut client);
+        client
+    }
+
+    /// Dispatch the compositor once
+    pub fn dispatch(&mut self) {
+        self.event_loop
+            .dispatch(Some(Duration::ZERO), &mut self.data)
+            .unwrap();
+        self.data.display.flush_clients().unwrap();
+    }
+
+    /// Dispatch the client and the compositor until the requests and
+    /// answers in flight are handled
+    pub fn roundtrip(&mut self, client: &mut TestClient) {
+        for _ in 0..ROUNDTRIP_ITERATIONS {
+            client.flush();
+            self.dispatch();
+            client.dispatch();
+        }
+    }
+
+    /// The window of the nth toplevel created by the client, if it is mapped
+    pub fn window(&self, client: &TestClient, index: usize) -> Option<WindowElement> {
+        find_window(self.state().space.elements().cloned(), client, index)
+    }
+
+    /// The window of the nth toplevel created by the client, mapped or not
+    pub fn any_window(&self, client: &TestClient, index: usize) -> Option<WindowElement> {
+        let state = self.state();
+        let windows = state
+            .workspaces
+            .iter()
+            .flat_map(|workspace| workspace.windows());
+        find_window(
+            state.space.elements().cloned().chain(windows),
+            client,
+            index,
+        )
+    }
+
+    /// Where the nth toplevel of the client is in the space
+    pub fn window_geometry(
+        &self,
+        client: &TestClient,
+        index: usize,
+    ) -> Option<Rectangle<i32, Logical>> {
+        let window = self.window(client, index)?;
+        let location = self.state().space.element_location(&window)?;
+        Some(Rectangle::from_loc_and_size(
+            location,
+            window.geometry().size,
+        ))
+    }
+}
+
+fn find_window(
+    mut windows: impl Iterator<Item = WindowElement>,
+    client: &TestClient,
+    index: usize,
+) -> Option<WindowElement> {
+    let protocol_id = client.surface_protocol_id(index)?;
+    windows.find(|window| {
+        let surface = window.toplevel().wl_surface();
+        surface.client().map(|c| c.id()) == Some(client.server_id.clone())
+            && surface.id().protocol_id() == protocol_id
+    })
+}
+
+/// Check the rectangles cover the area without overlapping
+pub fn assert_covers(area: Rectangle<i32, Logical>, rects: &[Rectangle<i32, Logical>]) {
+    for (index, rect) in rects.iter().enumerate() {
+        assert!(area.contains_rect(*rect), "{rect:?} is out of {area:?}");
+        for other in rects[index + 1..].iter() {
+            assert!(!rect.overlaps(*other), "{rect:?} overlaps {other:?}");
+        }
+    }
+    let covered: i32 = rects.iter().map(|rect| rect.size.w * rect.size.h).sum();
+    assert_eq!(
+        covered,
+        area.size.w * area.size.h,
+        "{rects:?} do not cover {area:?}"
+    );
+}
@@ -0,0 +1,310 @@
+use std::{
+    io::ErrorKind,
+    os::unix::{io::AsRawFd, net::UnixStream},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use smithay::reexports::wayland_server::backend::ClientId;
+use wayland_client::{
+    backend::WaylandError,
+    delegate_noop,
+    protocol::{
+        wl_buffer::WlBuffer, wl_callback, wl_compositor::WlCompositor, wl_registry, wl_shm,
+        wl_shm_pool::WlShmPool, wl_surface::WlSurface,
+    },
+    Connection, Dispatch, EventQueue, Proxy, QueueHandle,
+};
+use wayland_protocols::xdg::shell::client::{xdg_surface, xdg_toplevel, xdg_wm_base};
+
+use super::test_runtime_dir;
+
+/// Size used for the buffer when the compositor lets the client decide
+const DEFAULT_SIZE: (i32, i32) = (100, 100);
+
+#[derive(Debug)]
+struct TestWindow {
+    surface: WlSurface,
+    xdg_surface: xdg_surface::XdgSurface,
+    toplevel: Option<xdg_toplevel::XdgToplevel>,
+    /// size of the configure not acked yet
+    pending: (i32, i32),
+    /// sizes of every configure acked by the window, in order
+    configures: Vec<(i32, i32)>,
+    /// how many frame callbacks were done
+    frames: usize,
+}
+
+#[derive(Debug, Default)]
+pub struct ClientData {
+    compositor: Option<WlCompositor>,
+    shm: Option<wl_shm::WlShm>,
+    wm_base: Option<xdg_wm_base::XdgWmBase>,
+    windows: Vec<TestWindow>,
+}
+
+/// A scripted wayland client creating xdg toplevels with shm buffers of
+/// the size the compositor asks for
+pub struct TestClient {
+    connection: Connection,
+    queue: EventQueue<ClientData>,
+    data: ClientData,
+    /// the id of this client in the compositor
+    pub server_id: ClientId,
+}
+
+impl TestClient {
+    pub fn new(stream: UnixStream, server_id: ClientId) -> Self {
+        let connection = Connection::from_socket(stream).unwrap();
+        let queue = connection.new_event_queue();
+        connection.display().get_registry(&queue.handle(), ());
+        Self {
+            connection,
+            queue,
+            data: ClientData::default(),
+            server_id,
+        }
+    }
+
+    pub fn flush(&mut self) {
+        self.connection.flush().unwrap();
+    }
+
+    pub fn dispatch(&mut self) {
+        if let Ok(guard) = self.queue.prepare_read() {
+            match guard.read() {
+                Ok(_) => {}
+                Err(WaylandError::Io(err)) if err.kind() == ErrorKind::WouldBlock => {}
+                Err(err) => panic!("failed to read events: {err}"),
+            }
+        }
+        self.queue.dispatch_pending(&mut self.data).unwrap();
+    }
+
+    /// Create a toplevel, return its index in the windows of the client
+    pub fn create_window(&mut self) -> usize {
+        self.create_window_with(None)
+    }
+
+    /// Create a toplevel with the app_id set before its initial commit
+    pub fn create_window_with_app_id(&mut self, app_id: &str) -> usize {
+        self.create_window_with(Some(app_id))
+    }
+
+    fn create_window_with(&mut self, app_id: Option<&str>) -> usize {
+        let qh = self.queue.handle();
+        let index = self.data.windows.len();
+        let surface = self
+            .data
+            .compositor
+            .as_ref()
+            .expect("no wl_compositor")
+            .create_surface(&qh, ());
+        let xdg_surface = self
+            .data
+            .wm_base
+            .as_ref()
+            .expect("no xdg_wm_base")
+            .get_xdg_surface(&surface, &qh, index);
+        let toplevel = xdg_surface.get_toplevel(&qh, index);
+        if let Some(app_id) = app_id {
+            toplevel.set_app_id(app_id.to_string());
+        }
+        surface.commit();
+        self.data.windows.push(TestWindow {
+            surface,
+            xdg_surface,
+            toplevel: Some(toplevel),
+            pending: (0, 0),
+            configures: Vec::new(),
+            frames: 0,
+        });
+        index
+    }
+
+    /// Destroy the toplevel of the window
+    pub fn close_window(&mut self, index: usize) {
+        let window = &mut self.data.windows[index];
+        if let Some(toplevel) = window.toplevel.take() {
+            toplevel.destroy();
+        }
+        window.xdg_surface.destroy();
+        window.surface.destroy();
+    }
+
+    pub fn set_title(&mut self, index: usize, title: &str) {
+        if let Some(toplevel) = &self.data.windows[index].toplevel {
+            toplevel.set_title(title.to_string());
+        }
+    }
+
+    /// Ask for a frame callback and commit
+    pub fn request_frame(&mut self, index: usize) {
+        let surface = &self.data.windows[index].surface;
+        surface.frame(&self.queue.handle(), index);
+        surface.commit();
+    }
+
+    /// How many frame callbacks of the window were done
+    pub fn frames(&self, index: usize) -> usize {
+        self.data.windows[index].frames
+    }
+
+    /// The size of the first configure acked by the window
+    pub fn first_configure(&self, index: usize) -> Option<(i32, i32)> {
+        self.data.windows[index].configures.first().copied()
+    }
+
+    /// The size of the last configure acked by the window
+    pub fn last_configure(&self, index: usize) -> Option<(i32, i32)> {
+        self.data.windows[index].configures.last().copied()
+    }
+
+    pub fn surface_protocol_id(&self, index: usize) -> Option<u32> {
+        self.data
+            .windows
+            .get(index)
+            .map(|window| window.surface.id().protocol_id())
+    }
+}
+
+impl ClientData {
+    /// Attach a new buffer of the size to the surface and commit it
+    fn commit_buffer(&self, index: usize, qh: &QueueHandle<Self>) {
+        static NEXT_POOL: AtomicUsize = AtomicUsize::new(0);
+        let window = &self.windows[index];
+        let (mut width, mut height) = window.pending;
+        if width == 0 || height == 0 {
+            (width, height) = DEFAULT_SIZE;
+        }
+        let stride = width * 4;
+        let len = stride * height;
+        let path = test_runtime_dir().join(format!(
+            "pool-{}-{}",
+            std::process::id(),
+            NEXT_POOL.fetch_add(1, Ordering::Relaxed)
+        ));
+        let file = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)
+            .unwrap();
+        std::fs::remove_file(&path).unwrap();
+        file.set_len(len as u64).unwrap();
+
+        let shm = self.shm.as_ref().expect("no wl_shm");
+        let pool = shm.create_pool(file.as_raw_fd(), len, qh, ());
+        let buffer = pool.create_buffer(0, width, height, stride, wl_shm::Format::Argb8888, qh, ());
+        pool.destroy();
+
+        window.surface.attach(Some(&buffer), 0, 0);
+        window.surface.damage_buffer(0, 0, width, height);
+        window.surface.commit();
+    }
+}
+
+impl Dispatch<wl_registry::WlRegistry, ()> for ClientData {
+    fn event(
+        state: &mut Self,
+        registry: &wl_registry::WlRegistry,
+        event: wl_registry::Event,
+        _: &(),
+        _: &Connection,
+        qh: &QueueHandle<Self>,
+    ) {
+        let wl_registry::Event::Global {
+            name,
+            interface,
+            version,
+        } = event
+        else {
+            return;
+        };
+        match interface.as_str() {
+            "wl_compositor" => {
+                state.compositor = Some(registry.bind(name, version.min(4), qh, ()));
+            }
+            "wl_shm" => {
+                state.shm = Some(registry.bind(name, 1, qh, ()));
+            }
+            "xdg_wm_base" => {
+                state.wm_base = Some(registry.bind(name, version.min(3), qh, ()));
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<xdg_wm_base::XdgWmBase, ()> for ClientData {
+    fn event(
+        _: &mut Self,
+        wm_base: &xdg_wm_base::XdgWmBase,
+        event: xdg_wm_base::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        if let xdg_wm_base::Event::Ping { serial } = event {
+            wm_base.pong(serial);
+        }
+    }
+}
+
+impl Dispatch<xdg_surface::XdgSurface, usize> for ClientData {
+    fn event(
+        state: &mut Self,
+        xdg_surface: &xdg_surface::XdgSurface,
+        event: xdg_surface::Event,
+        index: &usize,
+        _: &Connection,
+        qh: &QueueHandle<Self>,
+    ) {
+        let xdg_surface::Event::Configure { serial } = event else {
+            return;
+        };
+        xdg_surface.ack_configure(serial);
+        let window = &mut state.windows[*index];
+        if window.toplevel.is_none() {
+            return;
+        }
+        window.configures.push(window.pending);
+        state.commit_buffer(*index, qh);
+    }
+}
+
+impl Dispatch<xdg_toplevel::XdgToplevel, usize> for ClientData {
+    fn event(
+        state: &mut Self,
+        _: &xdg_toplevel::XdgToplevel,
+        event: xdg_toplevel::Event,
+        index: &usize,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        if let xdg_toplevel::Event::Configure { width, height, .. } = event {
+            state.windows[*index].pending = (width, height);
+        }
+    }
+}
+
+impl Dispatch<wl_callback::WlCallback, usize> for ClientData {
+    fn event(
+        state: &mut Self,
+        _: &wl_callback::WlCallback,
+        event: wl_callback::Event,
+        index: &usize,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        if let wl_callback::Event::Done { .. } = event {
+            state.windows[*index].frames += 1;
+        }
+    }
+}
+
+delegate_noop!(ClientData: WlCompositor);
+delegate_noop!(ClientData: ignore WlSurface);
+delegate_noop!(ClientData: ignore wl_shm::WlShm);
+delegate_noop!(ClientData: WlShmPool);
+delegate_noop!(ClientData: ignore WlBuffer);
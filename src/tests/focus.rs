@@ -0,0 +1,86 @@
+use smithay::utils::SERIAL_COUNTER;
+
+use super::TestCompositor;
+use crate::{focus::Direction, shell::WindowElement, state::WmStatus};
+
+#[test]
+fn focused_window_is_raised() {
+    let mut compositor = TestCompositor::new(1280, 720);
+    let mut client = compositor.connect();
+
+    let first = client.create_window();
+    compositor.roundtrip(&mut client);
+    let second = client.create_window();
+    compositor.roundtrip(&mut client);
+
+    let window = compositor.window(&client, first).unwrap();
+    compositor
+        .state_mut()
+        .focus_window(&window, SERIAL_COUNTER.next_serial());
+    compositor.roundtrip(&mut client);
+
+    let state = compositor.state();
+    assert_eq!(state.focused_window(), Some(window.clone()));
+    assert_eq!(state.space.elements().last(), Some(&window));
+    let second = compositor.window(&client, second).unwrap();
+    let position = |window: &WindowElement| state.space.elements().position(|w| w == window);
+    assert!(position(&second) < position(&window));
+}
+
+#[test]
+fn closing_focused_window_focuses_previous_one() {
+    let mut compositor = TestCompositor::new(1280, 720);
+    let mut client = compositor.connect();
+
+    let windows: Vec<usize> = (0..3)
+        .map(|_| {
+            let window = client.create_window();
+            compositor.roundtrip(&mut client);
+            window
+        })
+        .collect();
+    for index in [windows[0], windows[2], windows[1]] {
+        let window = compositor.window(&client, index).unwrap();
+        compositor
+            .state_mut()
+            .focus_window(&window, SERIAL_COUNTER.next_serial());
+        compositor.roundtrip(&mut client);
+    }
+
+    client.close_window(windows[1]);
+    compositor.roundtrip(&mut client);
+
+    let previous = compositor.window(&client, windows[2]).unwrap();
+    assert_eq!(compositor.state().focused_window(), Some(previous.clone()));
+    assert!(previous.is_activated());
+    assert!(!compositor
+        .window(&client, windows[0])
+        .unwrap()
+        .is_activated());
+}
+
+#[test]
+fn directional_focus_follows_tiles() {
+    let mut compositor = TestCompositor::new(1280, 720);
+    compositor.state_mut().workspace_mut().wmstatus = WmStatus::Tile;
+    let mut client = compositor.connect();
+
+    let left = client.create_window();
+    compositor.roundtrip(&mut client);
+    let right = client.create_window();
+    compositor.roundtrip(&mut client);
+
+    let left = compositor.window(&client, left).unwrap();
+    let right = compositor.window(&client, right).unwrap();
+    compositor
+        .state_mut()
+        .focus_window(&left, SERIAL_COUNTER.next_serial());
+
+    compositor.state_mut().focus_direction(Direction::Right);
+    assert_eq!(compositor.state().focused_window(), Some(right.clone()));
+    // there is nothing above the right tile
+    compositor.state_mut().focus_direction(Direction::Up);
+    assert_eq!(compositor.state().focused_window(), Some(right));
+    compositor.state_mut().focus_direction(Direction::Left);
+    assert_eq!(compositor.state().focused_window(), Some(left));
+}
@@ -0,0 +1,671 @@
+use smithay::{
+    reexports::wayland_protocols::xdg::shell::server::xdg_toplevel,
+    utils::{Logical, Rectangle, SERIAL_COUNTER},
+};
+
+use super::{assert_covers, TestClient, TestCompositor};
+use crate::{
+    config::Config,
+    focus::Direction,
+    layout::{ContainerMode, TAB_HEIGHT},
+    state::{SplitState, WmStatus},
+};
+
+fn tiling_compositor() -> TestCompositor {
+    let mut compositor = TestCompositor::new(1920, 1080);
+    compositor.state_mut().workspace_mut().wmstatus = WmStatus::Tile;
+    compositor
+}
+
+/// The size the client is configured with for a tile of the size
+fn content_size(compositor: &TestCompositor, width: i32, height: i32) -> Option<(i32, i32)> {
+    let border = compositor.state().config.border.width;
+    Some((width - 2 * border, height - 2 * border))
+}
+
+fn geometries(
+    compositor: &TestCompositor,
+    client: &TestClient,
+    windows: &[usize],
+) -> Vec<Rectangle<i32, Logical>> {
+    windows
+        .iter()
+        .map(|index| compositor.window_geometry(client, *index).unwrap())
+        .collect()
+}
+
+#[test]
+fn single_window_covers_output() {
+    let mut compositor = tiling_compositor();
+    let mut client = compositor.connect();
+
+    let window = client.create_window();
+    compositor.roundtrip(&mut client);
+
+    let output = compositor.output_geometry();
+    assert_eq!(
+        client.last_configure(window),
+        content_size(&compositor, 1920, 1080)
+    );
+    assert_eq!(compositor.window_geometry(&client, window), Some(output));
+}
+
+#[test]
+fn two_windows_split_output() {
+    let mut compositor = tiling_compositor();
+    let mut client = compositor.connect();
+
+    let first = client.create_window();
+    compositor.roundtrip(&mut client);
+    let second = client.create_window();
+    compositor.roundtrip(&mut client);
+
+    let output = compositor.output_geometry();
+    let rects = geometries(&compositor, &client, &[first, second]);
+    assert_eq!(rects[0], Rectangle::from_loc_and_size((0, 0), (960, 1080)));
+    assert_eq!(
+        rects[1],
+        Rectangle::from_loc_and_size((960, 0), (960, 1080))
+    );
+    assert_covers(output, &rects);
+}
+
+#[test]
+fn vertical_split_stacks_windows() {
+    let mut compositor = tiling_compositor();
+    let mut client = compositor.connect();
+
+    let first = client.create_window();
+    compositor.roundtrip(&mut client);
+    compositor.state_mut().set_split_state(SplitState::V);
+    let second = client.create_window();
+    compositor.roundtrip(&mut client);
+
+    let rects = geometries(&compositor, &client, &[first, second]);
+    assert_eq!(rects[0], Rectangle::from_loc_and_size((0, 0), (1920, 540)));
+    assert_eq!(
+        rects[1],
+        Rectangle::from_loc_and_size((0, 540), (1920, 540))
+    );
+}
+
+#[test]
+fn closing_window_gives_space_back() {
+    let mut compositor = tiling_compositor();
+    let mut client = compositor.connect();
+
+    let windows: Vec<usize> = (0..3)
+        .map(|_| {
+            let window = client.create_window();
+            compositor.roundtrip(&mut client);
+            window
+        })
+        .collect();
+    let output = compositor.output_geometry();
+    assert_covers(output, &geometries(&compositor, &client, &windows));
+
+    client.close_window(windows[1]);
+    compositor.roundtrip(&mut client);
+
+    assert!(compositor.window(&client, windows[1]).is_none());
+    let rects = geometries(&compositor, &client, &[windows[0], windows[2]]);
+    assert_covers(output, &rects);
+}
+
+#[test]
+fn switching_to_tile_resizes_windows() {
+    let mut compositor = TestCompositor::new(1280, 720);
+    compositor.state_mut().workspace_mut().wmstatus = WmStatus::Stack;
+    let mut client = compositor.connect();
+
+    let first = client.create_window();
+    compositor.roundtrip(&mut client);
+    let second = client.create_window();
+    compositor.roundtrip(&mut client);
+
+    compositor.state_mut().change_wm_status();
+    compositor.roundtrip(&mut client);
+
+    let size = content_size(&compositor, 640, 720);
+    assert_eq!(client.last_configure(first), size);
+    assert_eq!(client.last_configure(second), size);
+    let rects = geometries(&compositor, &client, &[first, second]);
+    assert_covers(compositor.output_geometry(), &rects);
+}
+
+#[test]
+fn resizing_tile_moves_split() {
+    let mut compositor = tiling_compositor();
+    let mut client = compositor.connect();
+
+    let first = client.create_window();
+    compositor.roundtrip(&mut client);
+    let second = client.create_window();
+    compositor.roundtrip(&mut client);
+
+    let window = compositor.window(&client, first).unwrap();
+    let area = compositor.state().layout_area().unwrap();
+    let state = compositor.state_mut();
+    assert!(state
+        .workspace_mut()
+        .layout
+        .resize(area, &window, SplitState::H, true, 100.0));
+    // there is nothing on the left of the first window
+    assert!(!state
+        .workspace_mut()
+        .layout
+        .resize(area, &window, SplitState::H, false, 100.0));
+    state.apply_layout();
+    compositor.roundtrip(&mut client);
+
+    let rects = geometries(&compositor, &client, &[first, second]);
+    assert_eq!(rects[0], Rectangle::from_loc_and_size((0, 0), (1060, 1080)));
+    assert_eq!(
+        rects[1],
+        Rectangle::from_loc_and_size((1060, 0), (860, 1080))
+    );
+}
+
+#[test]
+fn swapping_tiles_exchanges_geometries() {
+    let mut compositor = tiling_compositor();
+    let mut client = compositor.connect();
+
+    let first = client.create_window();
+    compositor.roundtrip(&mut client);
+    let second = client.create_window();
+    compositor.roundtrip(&mut client);
+
+    let before = geometries(&compositor, &client, &[first, second]);
+    let first_window = compositor.window(&client, first).unwrap();
+    let second_window = compositor.window(&client, second).unwrap();
+    let state = compositor.state_mut();
+    assert!(state
+        .workspace_mut()
+        .layout
+        .swap(&first_window, &second_window));
+    state.apply_layout();
+    compositor.roundtrip(&mut client);
+
+    let after = geometries(&compositor, &client, &[first, second]);
+    assert_eq!(after, vec![before[1], before[0]]);
+}
+
+#[test]
+fn fullscreen_covers_output_and_gives_tile_back() {
+    let mut compositor = tiling_compositor();
+    let mut client = compositor.connect();
+
+    let first = client.create_window();
+    compositor.roundtrip(&mut client);
+    let second = client.create_window();
+    compositor.roundtrip(&mut client);
+
+    let before = geometries(&compositor, &client, &[first, second]);
+    let window = compositor.window(&client, first).unwrap();
+    compositor.state_mut().toggle_fullscreen(&window);
+    compositor.roundtrip(&mut client);
+
+    let output = compositor.output_geometry();
+    assert_eq!(client.last_configure(first), Some((1920, 1080)));
+    assert_eq!(compositor.window_geometry(&client, first), Some(output));
+    assert_eq!(compositor.window_geometry(&client, second), Some(before[1]));
+
+    compositor.state_mut().toggle_fullscreen(&window);
+    compositor.roundtrip(&mut client);
+
+    assert_eq!(
+        client.last_configure(first),
+        content_size(&compositor, 960, 1080)
+    );
+    assert_eq!(geometries(&compositor, &client, &[first, second]), before);
+}
+
+#[test]
+fn maximize_restores_floating_geometry() {
+    let mut compositor = TestCompositor::new(1280, 720);
+    compositor.state_mut().workspace_mut().wmstatus = WmStatus::Stack;
+    let mut client = compositor.connect();
+
+    let first = client.create_window();
+    compositor.roundtrip(&mut client);
+
+    let before = compositor.window_geometry(&client, first).unwrap();
+    let window = compositor.window(&client, first).unwrap();
+    compositor.state_mut().toggle_maximized(&window);
+    compositor.roundtrip(&mut client);
+
+    let area = compositor.state().layout_area().unwrap();
+    assert_eq!(compositor.window_geometry(&client, first), Some(area));
+
+    compositor.state_mut().toggle_maximized(&window);
+    compositor.roundtrip(&mut client);
+
+    assert_eq!(compositor.window_geometry(&client, first), Some(before));
+}
+
+#[test]
+fn promote_and_swap_from_keyboard() {
+    let mut compositor = tiling_compositor();
+    let mut client = compositor.connect();
+
+    let first = client.create_window();
+    compositor.roundtrip(&mut client);
+    let second = client.create_window();
+    compositor.roundtrip(&mut client);
+
+    let before = geometries(&compositor, &client, &[first, second]);
+    let window = compositor.window(&client, second).unwrap();
+    compositor
+        .state_mut()
+        .focus_window(&window, SERIAL_COUNTER.next_serial());
+    compositor.state_mut().promote_focused();
+    compositor.roundtrip(&mut client);
+    assert_eq!(
+        geometries(&compositor, &client, &[first, second]),
+        vec![before[1], before[0]]
+    );
+
+    compositor.state_mut().swap_focused(Direction::Right);
+    compositor.roundtrip(&mut client);
+    assert_eq!(geometries(&compositor, &client, &[first, second]), before);
+}
+
+#[test]
+fn moving_into_sibling_container() {
+    let mut compositor = tiling_compositor();
+    let mut client = compositor.connect();
+
+    let first = client.create_window();
+    compositor.roundtrip(&mut client);
+    let second = client.create_window();
+    compositor.roundtrip(&mut client);
+    compositor.state_mut().set_split_state(SplitState::V);
+    let third = client.create_window();
+    compositor.roundtrip(&mut client);
+
+    let window = compositor.window(&client, first).unwrap();
+    compositor
+        .state_mut()
+        .focus_window(&window, SERIAL_COUNTER.next_serial());
+    compositor.state_mut().move_focused(Direction::Right);
+    compositor.roundtrip(&mut client);
+
+    // the first window joins the column of the others, after the second
+    let rects = geometries(&compositor, &client, &[second, first, third]);
+    assert_eq!(rects[0], Rectangle::from_loc_and_size((0, 0), (1920, 270)));
+    assert_eq!(
+        rects[1],
+        Rectangle::from_loc_and_size((0, 270), (1920, 270))
+    );
+    assert_eq!(
+        rects[2],
+        Rectangle::from_loc_and_size((0, 540), (1920, 540))
+    );
+    assert_covers(compositor.output_geometry(), &rects);
+}
+
+#[test]
+fn tiles_know_their_neighbours() {
+    let mut compositor = tiling_compositor();
+    let mut client = compositor.connect();
+
+    let first = client.create_window();
+    compositor.roundtrip(&mut client);
+    let second = client.create_window();
+    compositor.roundtrip(&mut client);
+
+    let states = |compositor: &TestCompositor, index| {
+        compositor
+            .window(&client, index)
+            .unwrap()
+            .toplevel()
+            .current_state()
+            .states
+    };
+    let first_states = states(&compositor, first);
+    assert!(first_states.contains(xdg_toplevel::State::TiledRight));
+    assert!(!first_states.contains(xdg_toplevel::State::TiledLeft));
+    let second_states = states(&compositor, second);
+    assert!(second_states.contains(xdg_toplevel::State::TiledLeft));
+    assert!(!second_states.contains(xdg_toplevel::State::TiledRight));
+}
+
+#[test]
+fn master_layout_stacks_the_others() {
+    let mut compositor = TestCompositor::new(1920, 1080);
+    compositor.state_mut().workspace_mut().wmstatus = WmStatus::Master;
+    let mut client = compositor.connect();
+
+    let first = client.create_window();
+    compositor.roundtrip(&mut client);
+    let second = client.create_window();
+    compositor.roundtrip(&mut client);
+    let third = client.create_window();
+    compositor.roundtrip(&mut client);
+
+    assert_eq!(
+        geometries(&compositor, &client, &[first, second, third]),
+        vec![
+            Rectangle::from_loc_and_size((0, 0), (1056, 1080)),
+            Rectangle::from_loc_and_size((1056, 0), (864, 540)),
+            Rectangle::from_loc_and_size((1056, 540), (864, 540)),
+        ]
+    );
+
+    compositor.state_mut().change_master_count(1);
+    compositor.state_mut().change_master_ratio(0.05);
+    compositor.roundtrip(&mut client);
+    assert_eq!(
+        geometries(&compositor, &client, &[first, second, third]),
+        vec![
+            Rectangle::from_loc_and_size((0, 0), (1152, 540)),
+            Rectangle::from_loc_and_size((0, 540), (1152, 540)),
+            Rectangle::from_loc_and_size((1152, 0), (768, 1080)),
+        ]
+    );
+
+    let window = compositor.window(&client, third).unwrap();
+    compositor
+        .state_mut()
+        .focus_window(&window, SERIAL_COUNTER.next_serial());
+    compositor.state_mut().promote_focused();
+    compositor.roundtrip(&mut client);
+    assert_eq!(
+        geometries(&compositor, &client, &[third, second, first]),
+        vec![
+            Rectangle::from_loc_and_size((0, 0), (1152, 540)),
+            Rectangle::from_loc_and_size((0, 540), (1152, 540)),
+            Rectangle::from_loc_and_size((1152, 0), (768, 1080)),
+        ]
+    );
+}
+
+#[test]
+fn scroll_layout_follows_focus() {
+    let mut compositor = TestCompositor::new(1920, 1080);
+    compositor.state_mut().workspace_mut().wmstatus = WmStatus::Scroll;
+    let mut client = compositor.connect();
+
+    let windows: Vec<usize> = (0..3)
+        .map(|_| {
+            let window = client.create_window();
+            compositor.roundtrip(&mut client);
+            window
+        })
+        .collect();
+    assert_eq!(
+        geometries(&compositor, &client, &windows),
+        vec![
+            Rectangle::from_loc_and_size((0, 0), (960, 1080)),
+            Rectangle::from_loc_and_size((960, 0), (960, 1080)),
+            Rectangle::from_loc_and_size((1920, 0), (960, 1080)),
+        ]
+    );
+
+    let window = compositor.window(&client, windows[2]).unwrap();
+    compositor
+        .state_mut()
+        .focus_window(&window, SERIAL_COUNTER.next_serial());
+    compositor.roundtrip(&mut client);
+    assert_eq!(
+        geometries(&compositor, &client, &windows),
+        vec![
+            Rectangle::from_loc_and_size((-960, 0), (960, 1080)),
+            Rectangle::from_loc_and_size((0, 0), (960, 1080)),
+            Rectangle::from_loc_and_size((960, 0), (960, 1080)),
+        ]
+    );
+
+    // the view only moves once the column is not shown
+    let window = compositor.window(&client, windows[1]).unwrap();
+    compositor
+        .state_mut()
+        .focus_window(&window, SERIAL_COUNTER.next_serial());
+    compositor.roundtrip(&mut client);
+    assert_eq!(
+        compositor.window_geometry(&client, windows[1]),
+        Some(Rectangle::from_loc_and_size((0, 0), (960, 1080)))
+    );
+}
+
+#[test]
+fn tabbed_container_shows_selected_window() {
+    let mut compositor = tiling_compositor();
+    let mut client = compositor.connect();
+
+    let first = client.create_window();
+    compositor.roundtrip(&mut client);
+    let second = client.create_window();
+    compositor.roundtrip(&mut client);
+
+    let window = compositor.window(&client, first).unwrap();
+    let state = compositor.state_mut();
+    state.focus_window(&window, SERIAL_COUNTER.next_serial());
+    state.set_focused_container_mode(ContainerMode::Tabbed);
+    compositor.roundtrip(&mut client);
+
+    let content = Rectangle::from_loc_and_size((0, TAB_HEIGHT), (1920, 1080 - TAB_HEIGHT));
+    assert_eq!(compositor.window_geometry(&client, first), Some(content));
+    // the tab not selected is sized but left out of the space
+    assert_eq!(compositor.window(&client, second), None);
+    let size = content_size(&compositor, content.size.w, content.size.h);
+    assert_eq!(client.last_configure(second), size);
+    let tabs = compositor.state().tabs();
+    assert_eq!(
+        tabs.iter().map(|tab| tab.geometry).collect::<Vec<_>>(),
+        vec![
+            Rectangle::from_loc_and_size((0, 0), (960, TAB_HEIGHT)),
+            Rectangle::from_loc_and_size((960, 0), (960, TAB_HEIGHT)),
+        ]
+    );
+    assert!(tabs[0].selected);
+    assert_eq!(compositor.state().space.elements().last(), Some(&window));
+
+    // clicking the second tab selects it
+    let second_window = compositor.state().tab_under((1000.0, 10.0).into()).unwrap();
+    assert_eq!(
+        compositor.any_window(&client, second),
+        Some(second_window.clone())
+    );
+    compositor
+        .state_mut()
+        .focus_window(&second_window, SERIAL_COUNTER.next_serial());
+    compositor.roundtrip(&mut client);
+    assert!(compositor.state().tabs()[1].selected);
+    assert_eq!(
+        compositor.state().space.elements().last(),
+        Some(&second_window)
+    );
+    assert_eq!(compositor.window(&client, first), None);
+
+    compositor
+        .state_mut()
+        .set_focused_container_mode(ContainerMode::Stacked);
+    compositor.roundtrip(&mut client);
+    assert_eq!(
+        compositor.window_geometry(&client, second),
+        Some(Rectangle::from_loc_and_size(
+            (0, 2 * TAB_HEIGHT),
+            (1920, 1080 - 2 * TAB_HEIGHT)
+        ))
+    );
+}
+
+#[test]
+fn gaps_are_left_around_tiles() {
+    let mut compositor = tiling_compositor();
+    compositor.state_mut().set_gaps(Some(10), Some(20));
+    let mut client = compositor.connect();
+
+    let first = client.create_window();
+    compositor.roundtrip(&mut client);
+    // smart gaps, a window alone covers the output
+    let output = compositor.output_geometry();
+    assert_eq!(compositor.window_geometry(&client, first), Some(output));
+
+    let second = client.create_window();
+    compositor.roundtrip(&mut client);
+    assert_eq!(
+        geometries(&compositor, &client, &[first, second]),
+        vec![
+            Rectangle::from_loc_and_size((20, 20), (935, 1040)),
+            Rectangle::from_loc_and_size((965, 20), (935, 1040)),
+        ]
+    );
+
+    client.close_window(second);
+    compositor.roundtrip(&mut client);
+    assert_eq!(compositor.window_geometry(&client, first), Some(output));
+}
+
+#[test]
+fn floating_window_is_centered_above_tiles() {
+    let mut compositor = tiling_compositor();
+    let mut client = compositor.connect();
+
+    let first = client.create_window();
+    compositor.roundtrip(&mut client);
+    let second = client.create_window();
+    compositor.roundtrip(&mut client);
+
+    let window = compositor.window(&client, second).unwrap();
+    compositor.state_mut().toggle_floating(&window);
+    compositor.roundtrip(&mut client);
+
+    // the tile takes the whole output back
+    let output = compositor.output_geometry();
+    assert_eq!(compositor.window_geometry(&client, first), Some(output));
+    let floating = compositor.window_geometry(&client, second).unwrap();
+    assert_eq!(
+        floating.loc,
+        (
+            (output.size.w - floating.size.w) / 2,
+            (output.size.h - floating.size.h) / 2
+        )
+            .into()
+    );
+    assert_eq!(compositor.state().space.elements().last(), Some(&window));
+
+    // focusing the tile keeps the floating window above it
+    let tile = compositor.window(&client, first).unwrap();
+    compositor
+        .state_mut()
+        .focus_window(&tile, SERIAL_COUNTER.next_serial());
+    assert_eq!(compositor.state().space.elements().last(), Some(&window));
+
+    compositor.state_mut().toggle_floating(&window);
+    compositor.roundtrip(&mut client);
+    assert_eq!(
+        geometries(&compositor, &client, &[first, second]),
+        vec![
+            Rectangle::from_loc_and_size((0, 0), (960, 1080)),
+            Rectangle::from_loc_and_size((960, 0), (960, 1080)),
+        ]
+    );
+}
+
+#[test]
+fn window_rules_match_app_id_and_title() {
+    let mut compositor = tiling_compositor();
+    compositor.state_mut().config = Config::parse(
+        r#"
+        [[rules]]
+        app_id = "^browser$"
+        workspace = 2
+
+        [[rules]]
+        title = "^Volume"
+        floating = true
+        border = false
+        "#,
+    )
+    .unwrap();
+    compositor.state_mut().workspaces[1].wmstatus = WmStatus::Tile;
+    let mut client = compositor.connect();
+
+    let browser = client.create_window_with_app_id("browser");
+    let terminal = client.create_window();
+    compositor.roundtrip(&mut client);
+    // the browser opened on the second workspace
+    assert_eq!(compositor.window(&client, browser), None);
+    assert_eq!(compositor.state().workspaces[1].windows().len(), 1);
+    // and was sized for its tile there, although that workspace is hidden
+    assert_eq!(
+        client.first_configure(browser),
+        content_size(&compositor, 1920, 1080)
+    );
+    let output = compositor.output_geometry();
+    assert_eq!(compositor.window_geometry(&client, terminal), Some(output));
+
+    // the rule is evaluated again when the title changes
+    client.set_title(terminal, "Volume Control");
+    compositor.roundtrip(&mut client);
+    let window = compositor.window(&client, terminal).unwrap();
+    assert!(window.is_floating());
+    assert_eq!(window.border_width(), 0);
+    let generation = compositor.state().config.generation;
+    assert_eq!(window.rules(generation), vec![1]);
+}
+
+#[test]
+fn first_configure_has_the_tile_size() {
+    let mut compositor = tiling_compositor();
+    let mut client = compositor.connect();
+
+    let first = client.create_window();
+    compositor.roundtrip(&mut client);
+    assert_eq!(
+        client.first_configure(first),
+        content_size(&compositor, 1920, 1080)
+    );
+
+    let second = client.create_window();
+    compositor.roundtrip(&mut client);
+    assert_eq!(
+        client.first_configure(second),
+        content_size(&compositor, 960, 1080)
+    );
+    assert_eq!(
+        compositor.window_geometry(&client, second),
+        Some(Rectangle::from_loc_and_size((960, 0), (960, 1080)))
+    );
+}
+
+#[test]
+fn stacked_window_opens_centered() {
+    let mut compositor = TestCompositor::new(1280, 720);
+    compositor.state_mut().workspace_mut().wmstatus = WmStatus::Stack;
+    let mut client = compositor.connect();
+
+    let window = client.create_window();
+    compositor.roundtrip(&mut client);
+    let geometry = compositor.window_geometry(&client, window).unwrap();
+    assert_eq!(
+        geometry.loc,
+        ((1280 - geometry.size.w) / 2, (720 - geometry.size.h) / 2).into()
+    );
+}
+
+#[test]
+fn hidden_workspace_gets_no_frames() {
+    let mut compositor = tiling_compositor();
+    let mut client = compositor.connect();
+
+    let window = client.create_window();
+    compositor.roundtrip(&mut client);
+
+    compositor.state_mut().switch_workspace(1);
+    client.request_frame(window);
+    compositor.roundtrip(&mut client);
+    let output = compositor.state().space.outputs().next().unwrap().clone();
+    compositor.state().send_frames(&output);
+    compositor.roundtrip(&mut client);
+    assert_eq!(client.frames(window), 0);
+
+    // the callback is done once the workspace is shown again
+    compositor.state_mut().switch_workspace(0);
+    compositor.state().send_frames(&output);
+    compositor.roundtrip(&mut client);
+    assert_eq!(client.frames(window), 1);
+}
@@ -1,17 +1,80 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
 use smithay::{
     backend::{
-        drm::{DrmNode, NodeType},
-        session::{libseat::LibSeatSession, Session},
-        udev::{all_gpus, primary_gpu, UdevBackend},
+        allocator::{
+            gbm::{GbmAllocator, GbmBufferFlags, GbmDevice},
+            Fourcc,
+        },
+        drm::{compositor::DrmCompositor, DrmDevice, DrmDeviceFd, DrmEvent, DrmNode},
+        egl::{EGLContext, EGLDisplay},
+        libinput::{LibinputInputBackend, LibinputSessionInterface},
+        renderer::gles::{GlesRenderer, GlesTexture},
+        session::{libseat::LibSeatSession, Event as SessionEvent, Session},
+        udev::{all_gpus, primary_gpu, UdevBackend, UdevEvent},
+    },
+    output::{Mode, Output, PhysicalProperties, Subpixel},
+    reexports::{
+        calloop::{
+            timer::{TimeoutAction, Timer},
+            EventLoop, LoopHandle, RegistrationToken,
+        },
+        drm::control::{connector, crtc, Device as ControlDevice, ModeTypeFlags},
+        input::Libinput,
+        rustix::fs::OFlags,
+        wayland_server::{backend::GlobalId, Display, DisplayHandle},
     },
-    reexports::calloop::EventLoop,
-    reexports::wayland_server::Display,
+    utils::{DeviceFd, Transform},
 };
+use tracing::{error, info, warn};
+
+use crate::{
+    cursor::PointerElement, render::output_elements, state::Backend, tabs::TabBars, CalloopData,
+    FlyJa,
+};
+
+pub const OUTPUT_NAME: &str = "udev";
+
+const SUPPORTED_FORMATS: &[Fourcc] = &[
+    Fourcc::Abgr2101010,
+    Fourcc::Argb2101010,
+    Fourcc::Abgr8888,
+    Fourcc::Argb8888,
+];
+
+const CLEAR_COLOR: [f32; 4] = [0.1, 0.1, 0.1, 1.0];
+
+type GbmDrmCompositor =
+    DrmCompositor<GbmAllocator<DrmDeviceFd>, GbmDevice<DrmDeviceFd>, (), DrmDeviceFd>;
+
+/// An output driven by a crtc of a gpu
+struct OutputSurface {
+    output: Output,
+    global: GlobalId,
+    connector: connector::Handle,
+    compositor: GbmDrmCompositor,
+}
+
+/// A gpu with its own renderer and the outputs connected to it
+struct GpuDevice {
+    token: RegistrationToken,
+    drm: DrmDevice,
+    gbm: GbmDevice<DrmDeviceFd>,
+    renderer: GlesRenderer,
+    pointer: PointerElement<GlesTexture>,
+    tabs: TabBars<GlesTexture>,
+    surfaces: HashMap<crtc::Handle, OutputSurface>,
+}
 
-use crate::{state::Backend, FlyJa};
-use tracing::{error, info};
 pub struct UdevData {
     pub session: LibSeatSession,
+    display_handle: DisplayHandle,
+    loop_handle: LoopHandle<'static, CalloopData<UdevData>>,
+    gpus: HashMap<DrmNode, GpuDevice>,
 }
 
 impl Backend for UdevData {
@@ -19,46 +82,459 @@ impl Backend for UdevData {
     fn seat_name(&self) -> String {
         self.session.seat()
     }
+
+    fn change_vt(&mut self, vt: i32) {
+        if let Err(err) = self.session.change_vt(vt) {
+            error!(?err, "Failed to switch to vt {}", vt);
+        }
+    }
 }
 
-pub fn run_udev() {
-    let mut event_loop = EventLoop::try_new().unwrap();
-    let mut display = Display::new().unwrap();
+/// Run on a tty, it can be tried without a real gpu by loading the `vkms`
+/// virtual DRM driver
+pub fn run_udev() -> Result<(), Box<dyn std::error::Error>> {
+    let mut event_loop: EventLoop<CalloopData<UdevData>> = EventLoop::try_new()?;
+    let mut display: Display<FlyJa<UdevData>> = Display::new()?;
 
-    let (session, notifier) = match LibSeatSession::new() {
-        Ok(ret) => ret,
-        Err(err) => {
-            error!("Could not initialize a session: {}", err);
-            return;
-        }
+    let (session, notifier) = LibSeatSession::new()?;
+
+    let primary_gpu = match primary_gpu(session.seat())? {
+        Some(path) => path,
+        None => all_gpus(session.seat())?
+            .into_iter()
+            .next()
+            .ok_or("No gpu found")?,
+    };
+    info!("Using {} as primary gpu.", primary_gpu.display());
+
+    let data = UdevData {
+        session: session.clone(),
+        display_handle: display.handle(),
+        loop_handle: event_loop.handle(),
+        gpus: HashMap::new(),
     };
-    let primary_gpu = primary_gpu(session.seat())
-        .unwrap()
-        .and_then(|x| {
-            DrmNode::from_path(x)
-                .ok()?
-                .node_with_type(NodeType::Render)?
-                .ok()
+    let state = FlyJa::init(data, &mut event_loop, &mut display);
+    let mut data = CalloopData { state, display };
+
+    let udev_backend = UdevBackend::new(&data.state.seat_name)?;
+
+    // the outputs of the primary gpu are the first ones in the space
+    let mut devices: Vec<(DrmNode, PathBuf)> = udev_backend
+        .device_list()
+        .filter_map(|(device_id, path)| Some((DrmNode::from_dev_id(device_id).ok()?, path.into())))
+        .collect();
+    devices.sort_by_key(|(_, path)| *path != primary_gpu);
+    for (node, path) in devices {
+        data.state.device_added(node, &path);
+    }
+
+    let mut libinput_context =
+        Libinput::new_with_udev::<LibinputSessionInterface<LibSeatSession>>(session.clone().into());
+    libinput_context
+        .udev_assign_seat(&session.seat())
+        .map_err(|_| "Failed to assign the seat to libinput")?;
+    let libinput_backend = LibinputInputBackend::new(libinput_context.clone());
+
+    let handle = event_loop.handle();
+    handle
+        .insert_source(libinput_backend, |event, _, data| {
+            let display_handle = data.display.handle();
+            data.state
+                .process_input_event(&display_handle, event, OUTPUT_NAME);
         })
-        .unwrap_or_else(|| {
-            all_gpus(session.seat())
-                .unwrap()
-                .into_iter()
-                .find_map(|x| DrmNode::from_path(x).ok())
-                .expect("No Gpu!")
-        });
+        .map_err(|err| err.error)?;
+
+    handle
+        .insert_source(notifier, move |event, _, data| match event {
+            SessionEvent::PauseSession => {
+                info!("Pausing the session");
+                libinput_context.suspend();
+                for gpu in data.state.backend_data.gpus.values_mut() {
+                    gpu.drm.pause();
+                }
+            }
+            SessionEvent::ActivateSession => {
+                info!("Resuming the session");
+                if libinput_context.resume().is_err() {
+                    error!("Failed to resume libinput");
+                }
+                for gpu in data.state.backend_data.gpus.values_mut() {
+                    gpu.drm.activate();
+                    for surface in gpu.surfaces.values_mut() {
+                        surface.compositor.reset_buffers();
+                    }
+                }
+                data.state.render_all();
+            }
+        })
+        .map_err(|err| err.error)?;
+
+    handle
+        .insert_source(udev_backend, |event, _, data| match event {
+            UdevEvent::Added { device_id, path } => {
+                if let Ok(node) = DrmNode::from_dev_id(device_id) {
+                    data.state.device_added(node, &path);
+                }
+            }
+            UdevEvent::Changed { device_id } => {
+                if let Ok(node) = DrmNode::from_dev_id(device_id) {
+                    data.state.device_changed(node);
+                }
+            }
+            UdevEvent::Removed { device_id } => {
+                if let Ok(node) = DrmNode::from_dev_id(device_id) {
+                    data.state.device_removed(node);
+                }
+            }
+        })
+        .map_err(|err| err.error)?;
+
+    std::env::set_var("WAYLAND_DISPLAY", &data.state.socket_name);
+    data.state.run_startup();
 
-    info!("Using {} as primary gpu.", primary_gpu);
+    event_loop.run(None, &mut data, move |data| {
+        data.state.space.refresh();
+        data.state.popups.cleanup();
+        let _ = data.display.flush_clients();
+    })?;
+    // let the clients know about the windows closed on quit
+    data.display.flush_clients()?;
+    Ok(())
+}
+
+impl FlyJa<UdevData> {
+    fn device_added(&mut self, node: DrmNode, path: &Path) {
+        if let Err(err) = self.open_device(node, path) {
+            warn!("Failed to open the gpu {}: {}", path.display(), err);
+        }
+    }
+
+    fn open_device(
+        &mut self,
+        node: DrmNode,
+        path: &Path,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let fd = self.backend_data.session.open(
+            path,
+            OFlags::RDWR | OFlags::CLOEXEC | OFlags::NOCTTY | OFlags::NONBLOCK,
+        )?;
+        let fd = DrmDeviceFd::new(DeviceFd::from(fd));
 
-    let data = UdevData { session };
+        let (drm, notifier) = DrmDevice::new(fd.clone(), true)?;
+        let gbm = GbmDevice::new(fd)?;
+        let egl_display = EGLDisplay::new(gbm.clone())?;
+        let context = EGLContext::new(&egl_display)?;
+        let renderer = unsafe { GlesRenderer::new(context) }?;
 
-    let mut state = FlyJa::init(data, &mut event_loop, &mut display);
+        let token = self
+            .backend_data
+            .loop_handle
+            .insert_source(notifier, move |event, _, data| match event {
+                DrmEvent::VBlank(crtc) => data.state.frame_finish(node, crtc),
+                DrmEvent::Error(err) => error!(?err, "DRM error on {}", node),
+            })
+            .map_err(|err| err.error)?;
 
-    let udev_backend = match UdevBackend::new(state.seat_name) {
-        Ok(ret) => ret,
-        Err(err) => {
-            error!(error = ?err,"Failed to initialize udev backend");
+        info!("Added the gpu {}", path.display());
+        self.backend_data.gpus.insert(
+            node,
+            GpuDevice {
+                token,
+                drm,
+                gbm,
+                renderer,
+                pointer: PointerElement::default(),
+                tabs: TabBars::default(),
+                surfaces: HashMap::new(),
+            },
+        );
+        self.device_changed(node);
+        Ok(())
+    }
+
+    /// Scan the connectors of the gpu, set up the new ones and drop the
+    /// outputs which are not connected anymore
+    fn device_changed(&mut self, node: DrmNode) {
+        let Some(gpu) = self.backend_data.gpus.get(&node) else {
             return;
+        };
+        let resources = match gpu.drm.resource_handles() {
+            Ok(resources) => resources,
+            Err(err) => {
+                warn!(?err, "Failed to read the resources of {}", node);
+                return;
+            }
+        };
+        let connected: Vec<connector::Info> = resources
+            .connectors()
+            .iter()
+            .filter_map(|handle| gpu.drm.get_connector(*handle, false).ok())
+            .filter(|info| info.state() == connector::State::Connected)
+            .collect();
+        let disconnected: Vec<crtc::Handle> = gpu
+            .surfaces
+            .iter()
+            .filter(|(_, surface)| {
+                !connected
+                    .iter()
+                    .any(|info| info.handle() == surface.connector)
+            })
+            .map(|(crtc, _)| *crtc)
+            .collect();
+        let new: Vec<connector::Info> = connected
+            .into_iter()
+            .filter(|info| {
+                !gpu.surfaces
+                    .values()
+                    .any(|surface| surface.connector == info.handle())
+            })
+            .collect();
+
+        for crtc in disconnected {
+            self.connector_disconnected(node, crtc);
         }
-    };
+        for info in new {
+            self.connector_connected(node, info);
+        }
+    }
+
+    fn connector_connected(&mut self, node: DrmNode, info: connector::Info) {
+        let Some(gpu) = self.backend_data.gpus.get_mut(&node) else {
+            return;
+        };
+        let name = format!("{}-{}", info.interface().as_str(), info.interface_id());
+
+        let Ok(resources) = gpu.drm.resource_handles() else {
+            return;
+        };
+        let crtc = info
+            .encoders()
+            .iter()
+            .filter_map(|handle| gpu.drm.get_encoder(*handle).ok())
+            .flat_map(|encoder| resources.filter_crtcs(encoder.possible_crtcs()))
+            .find(|crtc| !gpu.surfaces.contains_key(crtc));
+        let Some(crtc) = crtc else {
+            warn!("No free crtc for {}", name);
+            return;
+        };
+        let drm_mode = info
+            .modes()
+            .iter()
+            .find(|mode| mode.mode_type().contains(ModeTypeFlags::PREFERRED))
+            .or_else(|| info.modes().first())
+            .copied();
+        let Some(drm_mode) = drm_mode else {
+            warn!("No mode for {}", name);
+            return;
+        };
+
+        let surface = match gpu.drm.create_surface(crtc, drm_mode, &[info.handle()]) {
+            Ok(surface) => surface,
+            Err(err) => {
+                warn!(?err, "Failed to create a drm surface for {}", name);
+                return;
+            }
+        };
+
+        let (width, height) = info.size().unwrap_or((0, 0));
+        let output = Output::new(
+            name.clone(),
+            PhysicalProperties {
+                size: (width as i32, height as i32).into(),
+                subpixel: Subpixel::Unknown,
+                make: "Unknown".into(),
+                model: "Unknown".into(),
+            },
+        );
+
+        let allocator = GbmAllocator::new(
+            gpu.gbm.clone(),
+            GbmBufferFlags::RENDERING | GbmBufferFlags::SCANOUT,
+        );
+        let compositor = match DrmCompositor::new(
+            &output,
+            surface,
+            None,
+            allocator,
+            gpu.gbm.clone(),
+            SUPPORTED_FORMATS,
+            gpu.renderer.egl_context().dmabuf_render_formats().clone(),
+            gpu.drm.cursor_size(),
+            Some(gpu.gbm.clone()),
+        ) {
+            Ok(compositor) => compositor,
+            Err(err) => {
+                warn!(?err, "Failed to create a drm compositor for {}", name);
+                return;
+            }
+        };
+
+        // new outputs are placed at the right of the others
+        let x = self
+            .space
+            .outputs()
+            .filter_map(|output| self.space.output_geometry(output))
+            .map(|geometry| geometry.loc.x + geometry.size.w)
+            .max()
+            .unwrap_or(0);
+        let mode = Mode::from(drm_mode);
+        output.change_current_state(
+            Some(mode),
+            Some(Transform::Normal),
+            None,
+            Some((x, 0).into()),
+        );
+        output.set_preferred(mode);
+        let global = output.create_global::<FlyJa<UdevData>>(&self.backend_data.display_handle);
+        self.space.map_output(&output, (x, 0));
+
+        info!("Connected {} at {}x{}", name, mode.size.w, mode.size.h);
+        gpu.surfaces.insert(
+            crtc,
+            OutputSurface {
+                output,
+                global,
+                connector: info.handle(),
+                compositor,
+            },
+        );
+
+        self.apply_layout();
+        self.render_surface(node, crtc);
+    }
+
+    fn connector_disconnected(&mut self, node: DrmNode, crtc: crtc::Handle) {
+        let Some(surface) = self
+            .backend_data
+            .gpus
+            .get_mut(&node)
+            .and_then(|gpu| gpu.surfaces.remove(&crtc))
+        else {
+            return;
+        };
+        info!("Disconnected {}", surface.output.name());
+        self.space.unmap_output(&surface.output);
+        self.backend_data
+            .display_handle
+            .remove_global::<FlyJa<UdevData>>(surface.global);
+        self.apply_layout();
+    }
+
+    fn device_removed(&mut self, node: DrmNode) {
+        let Some(gpu) = self.backend_data.gpus.get(&node) else {
+            return;
+        };
+        let crtcs: Vec<crtc::Handle> = gpu.surfaces.keys().copied().collect();
+        for crtc in crtcs {
+            self.connector_disconnected(node, crtc);
+        }
+        if let Some(gpu) = self.backend_data.gpus.remove(&node) {
+            self.backend_data.loop_handle.remove(gpu.token);
+        }
+        info!("Removed the gpu {}", node);
+    }
+
+    fn frame_finish(&mut self, node: DrmNode, crtc: crtc::Handle) {
+        let Some(surface) = self
+            .backend_data
+            .gpus
+            .get_mut(&node)
+            .and_then(|gpu| gpu.surfaces.get_mut(&crtc))
+        else {
+            return;
+        };
+        if let Err(err) = surface.compositor.frame_submitted() {
+            warn!(?err, "Failed to submit the frame");
+        }
+        self.render_surface(node, crtc);
+    }
+
+    fn render_all(&mut self) {
+        let surfaces: Vec<(DrmNode, crtc::Handle)> = self
+            .backend_data
+            .gpus
+            .iter()
+            .flat_map(|(node, gpu)| gpu.surfaces.keys().map(|crtc| (*node, *crtc)))
+            .collect();
+        for (node, crtc) in surfaces {
+            self.render_surface(node, crtc);
+        }
+    }
+
+    /// Render the output and queue the frame, the next one is rendered on
+    /// vblank, or after a refresh interval when there was nothing to draw
+    fn render_surface(&mut self, node: DrmNode, crtc: crtc::Handle) {
+        if !self.backend_data.session.is_active() {
+            return;
+        }
+        let tabs = self.tabs();
+        let Some(gpu) = self.backend_data.gpus.get_mut(&node) else {
+            return;
+        };
+        let Some(scale) = gpu
+            .surfaces
+            .get(&crtc)
+            .map(|surface| surface.output.current_scale().fractional_scale())
+        else {
+            return;
+        };
+        gpu.pointer
+            .update(&mut gpu.renderer, &self.cursor, &self.cursor_status, scale);
+        gpu.tabs
+            .update(&mut gpu.renderer, &tabs, &self.config.border, scale);
+        let Some(surface) = gpu.surfaces.get_mut(&crtc) else {
+            return;
+        };
+
+        let Some(elements) = output_elements(
+            &mut gpu.renderer,
+            &self.space,
+            &surface.output,
+            &gpu.pointer,
+            self.pointer.current_location(),
+            &gpu.tabs,
+        ) else {
+            return;
+        };
+        let queued = match surface.compositor.render_frame::<_, _, GlesTexture>(
+            &mut gpu.renderer,
+            &elements,
+            CLEAR_COLOR,
+        ) {
+            Ok(result) if !result.is_empty => match surface.compositor.queue_frame(()) {
+                Ok(()) => true,
+                Err(err) => {
+                    warn!(?err, "Failed to queue the frame");
+                    false
+                }
+            },
+            Ok(_) => false,
+            Err(err) => {
+                warn!(?err, "Failed to render the frame");
+                false
+            }
+        };
+
+        let output = surface.output.clone();
+        self.send_frames(&output);
+
+        if !queued {
+            let refresh = output
+                .current_mode()
+                .map(|mode| mode.refresh)
+                .filter(|refresh| *refresh > 0)
+                .unwrap_or(60_000);
+            let timer = Timer::from_duration(Duration::from_micros(1_000_000_000 / refresh as u64));
+            let inserted = self
+                .backend_data
+                .loop_handle
+                .insert_source(timer, move |_, _, data| {
+                    data.state.render_surface(node, crtc);
+                    TimeoutAction::Drop
+                });
+            if let Err(err) = inserted {
+                warn!("Failed to schedule the next frame: {}", err.error);
+            }
+        }
+    }
 }
@@ -1,14 +1,18 @@
+use crate::cursor::PointerElement;
+use crate::render::output_elements;
 use crate::state::Backend;
+use crate::tabs::TabBars;
 use crate::CalloopData;
 use crate::FlyJa;
 use smithay::{
     backend::{
         renderer::{
-            damage::OutputDamageTracker, element::surface::WaylandSurfaceRenderElement,
-            gles::GlesRenderer,
+            damage::OutputDamageTracker,
+            gles::{GlesRenderer, GlesTexture},
         },
         winit::{self, WinitError, WinitEvent, WinitEventLoop, WinitGraphicsBackend},
     },
+    desktop::layer_map_for_output,
     output::{Mode, Output, PhysicalProperties, Subpixel},
     reexports::{
         calloop::{
@@ -23,6 +27,8 @@ use std::time::Duration;
 
 pub const OUTPUT_NAME: &str = "winit";
 
+const CLEAR_COLOR: [f32; 4] = [0.1, 0.1, 0.1, 1.0];
+
 pub struct WinitData;
 
 impl Backend for WinitData {
@@ -40,8 +46,11 @@ pub fn run_winit() -> Result<(), Box<dyn std::error::Error>> {
 
     let mut data = CalloopData { state, display };
     init_winit(&mut event_loop, &mut data)?;
+    data.state.run_startup();
 
     event_loop.run(None, &mut data, move |_| {})?;
+    // let the clients know about the windows closed on quit
+    data.display.flush_clients()?;
     Ok(())
 }
 
@@ -88,6 +97,10 @@ where
     std::env::set_var("WAYLAND_DISPLAY", &state.socket_name);
 
     let mut full_redraw = 0u8;
+    let mut pointer_element = PointerElement::default();
+    let mut tab_bars = TabBars::default();
+    // the pointer is drawn by flyja
+    backend.window().set_cursor_visible(false);
 
     let timer = Timer::immediate();
     event_loop
@@ -100,6 +113,8 @@ where
                 &output,
                 &mut damage_tracked_renderer,
                 &mut full_redraw,
+                &mut pointer_element,
+                &mut tab_bars,
             )
             .unwrap();
             TimeoutAction::ToDuration(Duration::from_millis(16))
@@ -115,6 +130,8 @@ fn winit_dispatch<T>(
     output: &Output,
     damage_tracked_renderer: &mut OutputDamageTracker,
     full_redraw: &mut u8,
+    pointer_element: &mut PointerElement<GlesTexture>,
+    tab_bars: &mut TabBars<GlesTexture>,
 ) -> Result<(), Box<dyn std::error::Error>>
 where
     T: Backend + 'static,
@@ -133,6 +150,8 @@ where
                 None,
                 None,
             );
+            layer_map_for_output(output).arrange();
+            state.apply_layout();
         }
         WinitEvent::Input(event) => {
             state.process_input_event(&display.handle(), event, OUTPUT_NAME)
@@ -155,26 +174,31 @@ where
     let damage = Rectangle::from_loc_and_size((0, 0), size);
 
     backend.bind()?;
-    smithay::desktop::space::render_output::<_, WaylandSurfaceRenderElement<GlesRenderer>, _, _>(
-        output,
+    pointer_element.update(
         backend.renderer(),
-        1.0,
-        0,
-        [&state.space],
-        &[],
-        damage_tracked_renderer,
-        [0.1, 0.1, 0.1, 1.0],
-    )?;
+        &state.cursor,
+        &state.cursor_status,
+        output.current_scale().fractional_scale(),
+    );
+    tab_bars.update(
+        backend.renderer(),
+        &state.tabs(),
+        &state.config.border,
+        output.current_scale().fractional_scale(),
+    );
+    if let Some(elements) = output_elements(
+        backend.renderer(),
+        &state.space,
+        output,
+        pointer_element,
+        state.pointer.current_location(),
+        tab_bars,
+    ) {
+        damage_tracked_renderer.render_output(backend.renderer(), 0, &elements, CLEAR_COLOR)?;
+    }
     backend.submit(Some(&[damage]))?;
 
-    state.space.elements().for_each(|window| {
-        window.send_frame(
-            output,
-            state.start_time.elapsed(),
-            Some(Duration::ZERO),
-            |_, _| Some(output.clone()),
-        )
-    });
+    state.send_frames(output);
 
     state.space.refresh();
     state.popups.cleanup();
@@ -0,0 +1,354 @@
+use smithay::{
+    reexports::wayland_server::protocol::wl_surface::WlSurface,
+    utils::{Logical, Point, Rectangle},
+    wayland::{compositor::get_role, shell::xdg::XDG_TOPLEVEL_ROLE},
+};
+
+use crate::{
+    config::{GapsConfig, LayoutConfig},
+    ipc::protocol::Event,
+    layout::{column_geometries, master_geometries, Arrangement, LayoutTree},
+    shell::WindowElement,
+    state::{Backend, SplitState, WmStatus},
+    FlyJa,
+};
+
+pub const WORKSPACE_COUNT: usize = 9;
+
+pub const MASTER_RATIO_STEP: f64 = 0.05;
+const MASTER_RATIO_MIN: f64 = 0.1;
+const MASTER_RATIO_MAX: f64 = 0.9;
+const COLUMN_WIDTH_MIN: f64 = 0.1;
+
+/// A set of windows with its own tile tree and modes, only the current one
+/// is mapped into the space
+#[derive(Debug)]
+pub struct Workspace {
+    pub layout: LayoutTree,
+    pub wmstatus: WmStatus,
+    pub splitstate: SplitState,
+    /// the part of the width taken by the masters in master mode
+    pub master_ratio: f64,
+    pub master_count: usize,
+    /// the part of the width taken by a column in scroll mode
+    pub column_width: f64,
+    /// how far the view is scrolled along the columns
+    scroll: i32,
+    /// windows kept out of the tile tree
+    floating: Vec<WindowElement>,
+    /// windows with their location, kept while the workspace is not shown
+    hidden: Vec<(WindowElement, Point<i32, Logical>)>,
+    /// windows in the order they were focused, the last one most recently
+    focus_history: Vec<WindowElement>,
+}
+
+impl Workspace {
+    pub fn new(config: &LayoutConfig) -> Self {
+        Self {
+            layout: LayoutTree::default(),
+            wmstatus: config.default,
+            splitstate: config.split,
+            master_ratio: config
+                .master_ratio
+                .clamp(MASTER_RATIO_MIN, MASTER_RATIO_MAX),
+            master_count: config.master_count,
+            column_width: config.column_width.clamp(COLUMN_WIDTH_MIN, 1.0),
+            scroll: 0,
+            floating: Vec::new(),
+            hidden: Vec::new(),
+            focus_history: Vec::new(),
+        }
+    }
+
+    /// windows of the tile tree, the floating ones and the hidden ones
+    pub fn windows(&self) -> Vec<WindowElement> {
+        let mut windows = self.layout.windows();
+        windows.extend(self.floating.iter().cloned());
+        for (window, _) in self.hidden.iter() {
+            if !windows.contains(window) {
+                windows.push(window.clone());
+            }
+        }
+        windows
+    }
+
+    /// The window of the toplevel surface, wherever it is in the workspace
+    pub fn window_for_surface(&self, surface: &WlSurface) -> Option<&WindowElement> {
+        let is_surface = |window: &WindowElement| window.toplevel().wl_surface() == surface;
+        self.layout
+            .find(is_surface)
+            .or_else(|| self.floating.iter().find(|window| is_surface(window)))
+            .or_else(|| {
+                self.hidden
+                    .iter()
+                    .map(|(window, _)| window)
+                    .find(|window| is_surface(window))
+            })
+    }
+
+    /// Lay the windows out into area in the mode of the workspace, the
+    /// windows keep the order of the tree in the master and scroll modes
+    pub fn arrange(&self, area: Rectangle<i32, Logical>, gaps: &GapsConfig) -> Arrangement {
+        let (area, gap) = self.gapped_area(area, gaps);
+        match self.wmstatus {
+            WmStatus::Master => Arrangement {
+                tiles: master_geometries(
+                    &self.layout.windows(),
+                    area,
+                    self.master_ratio,
+                    self.master_count,
+                    gap,
+                ),
+                gap,
+                ..Default::default()
+            },
+            WmStatus::Scroll => {
+                let windows = self.layout.windows();
+                let scroll = self
+                    .scroll
+                    .clamp(0, self.scroll_max(windows.len(), area, gap));
+                Arrangement {
+                    tiles: column_geometries(&windows, area, self.column_width, scroll, gap),
+                    gap,
+                    ..Default::default()
+                }
+            }
+            WmStatus::Tile => self.layout.arrange(area, gap),
+            // the floating windows stay where they are
+            WmStatus::Stack => Arrangement::default(),
+        }
+    }
+
+    /// The area left inside the outer gap and the gap between the tiles,
+    /// with smart gaps there are none around a window shown alone
+    fn gapped_area(
+        &self,
+        area: Rectangle<i32, Logical>,
+        gaps: &GapsConfig,
+    ) -> (Rectangle<i32, Logical>, i32) {
+        let shown = match self.wmstatus {
+            WmStatus::Tile => self.layout.geometries(area).len(),
+            _ => self.layout.windows().len(),
+        };
+        if gaps.smart && shown <= 1 {
+            return (area, 0);
+        }
+        let outer = gaps.outer.max(0);
+        let area = Rectangle::from_loc_and_size(
+            (area.loc.x + outer, area.loc.y + outer),
+            (
+                (area.size.w - 2 * outer).max(0),
+                (area.size.h - 2 * outer).max(0),
+            ),
+        );
+        (area, gaps.inner.max(0))
+    }
+
+    /// Select the tabs of the window or scroll to its column, return true
+    /// if the windows have to be laid out again
+    pub fn reveal(
+        &mut self,
+        window: &WindowElement,
+        area: Rectangle<i32, Logical>,
+        gaps: &GapsConfig,
+    ) -> bool {
+        match self.wmstatus {
+            WmStatus::Tile => self.layout.select(window),
+            WmStatus::Scroll => {
+                let (area, gap) = self.gapped_area(area, gaps);
+                self.scroll_to(window, area, gap)
+            }
+            _ => false,
+        }
+    }
+
+    fn column_size(&self, area: Rectangle<i32, Logical>) -> i32 {
+        (area.size.w as f64 * self.column_width).round() as i32
+    }
+
+    /// The view does not go past the last column
+    fn scroll_max(&self, columns: usize, area: Rectangle<i32, Logical>, gap: i32) -> i32 {
+        let columns = columns as i32;
+        (self.column_size(area) * columns + gap * (columns - 1) - area.size.w).max(0)
+    }
+
+    /// Scroll the least to show the whole column of the window, return true
+    /// if the view moved
+    fn scroll_to(
+        &mut self,
+        window: &WindowElement,
+        area: Rectangle<i32, Logical>,
+        gap: i32,
+    ) -> bool {
+        let windows = self.layout.windows();
+        let Some(index) = windows.iter().position(|w| w == window) else {
+            return false;
+        };
+        let width = self.column_size(area);
+        let start = (width + gap) * index as i32;
+        let scroll = self
+            .scroll
+            .clamp(0, self.scroll_max(windows.len(), area, gap))
+            .min(start)
+            .max(start + width - area.size.w);
+        let moved = scroll != self.scroll;
+        self.scroll = scroll;
+        moved
+    }
+
+    pub fn set_master_ratio(&mut self, ratio: f64) {
+        self.master_ratio = ratio.clamp(MASTER_RATIO_MIN, MASTER_RATIO_MAX);
+    }
+
+    pub fn hidden_location(&self, window: &WindowElement) -> Point<i32, Logical> {
+        self.hidden
+            .iter()
+            .find(|(w, _)| w == window)
+            .map(|(_, location)| *location)
+            .unwrap_or_default()
+    }
+
+    /// Put the window in the tree, or with the floating ones
+    pub fn insert(&mut self, window: WindowElement, target: Option<&WindowElement>) {
+        if window.is_floating() {
+            self.floating.push(window);
+        } else {
+            self.layout.insert(window, target, self.splitstate);
+        }
+    }
+
+    /// Take the window out of the tree to float, or put it back in
+    pub fn set_floating(&mut self, window: &WindowElement, floating: bool) {
+        self.layout.remove(window);
+        self.floating.retain(|w| w != window);
+        window.set_floating(floating);
+        self.insert(window.clone(), None);
+    }
+
+    pub fn remove(&mut self, window: &WindowElement) {
+        self.layout.remove(window);
+        self.floating.retain(|w| w != window);
+        self.hidden.retain(|(w, _)| w != window);
+        self.focus_history.retain(|w| w != window);
+    }
+
+    pub fn note_focus(&mut self, window: &WindowElement) {
+        self.focus_history.retain(|w| w != window);
+        self.focus_history.push(window.clone());
+    }
+
+    /// The window focused most recently
+    pub fn last_focused(&self) -> Option<WindowElement> {
+        self.focus_history.last().cloned()
+    }
+}
+
+impl<BackendData: Backend + 'static> FlyJa<BackendData> {
+    pub fn workspace(&self) -> &Workspace {
+        &self.workspaces[self.current_workspace]
+    }
+
+    pub fn workspace_mut(&mut self) -> &mut Workspace {
+        &mut self.workspaces[self.current_workspace]
+    }
+
+    /// Find the window of the surface in the space or in any workspace
+    pub fn any_window_for_surface(&self, surface: &WlSurface) -> Option<WindowElement> {
+        // most commits are of subsurfaces, popups, layer surfaces or the cursor
+        if get_role(surface) != Some(XDG_TOPLEVEL_ROLE) {
+            return None;
+        }
+        self.window_for_surface(surface).or_else(|| {
+            self.workspaces
+                .iter()
+                .find_map(|workspace| workspace.window_for_surface(surface))
+                .cloned()
+        })
+    }
+
+    pub fn change_master_ratio(&mut self, delta: f64) {
+        let ratio = self.workspace().master_ratio + delta;
+        self.workspace_mut().set_master_ratio(ratio);
+        self.apply_layout();
+    }
+
+    pub fn change_master_count(&mut self, delta: isize) {
+        let workspace = self.workspace_mut();
+        workspace.master_count = workspace.master_count.saturating_add_signed(delta);
+        self.apply_layout();
+    }
+
+    /// Unmap the windows of the current workspace and map the ones of index
+    pub fn switch_workspace(&mut self, index: usize) {
+        if index >= self.workspaces.len() || index == self.current_workspace {
+            return;
+        }
+        let windows: Vec<(WindowElement, Point<i32, Logical>)> = self
+            .space
+            .elements()
+            .filter_map(|w| Some((w.clone(), self.space.element_location(w)?)))
+            .collect();
+        for (window, _) in windows.iter() {
+            // out of the space the window gets no frame callbacks, which
+            // stops it from drawing until its workspace is shown again
+            self.space.unmap_elem(window);
+            window.set_activated(false);
+            window.toplevel().send_pending_configure();
+        }
+        self.workspace_mut().hidden = windows;
+
+        self.current_workspace = index;
+        let hidden = std::mem::take(&mut self.workspace_mut().hidden);
+        for (window, location) in hidden {
+            self.space.map_element(window, location, false);
+        }
+        self.apply_layout();
+        self.focus_last_window();
+        self.ipc_event(Event::WorkspaceChanged {
+            workspace: index + 1,
+        });
+    }
+
+    /// The index of the workspace the window is on
+    pub fn workspace_of(&self, window: &WindowElement) -> Option<usize> {
+        if self.space.elements().any(|w| w == window) {
+            return Some(self.current_workspace);
+        }
+        self.workspaces
+            .iter()
+            .position(|workspace| workspace.windows().contains(window))
+    }
+
+    /// Move the window to the workspace of index
+    pub fn move_window_to_workspace(&mut self, window: &WindowElement, index: usize) {
+        if index >= self.workspaces.len() {
+            return;
+        }
+        let Some(from) = self.workspace_of(window) else {
+            return;
+        };
+        if from == index {
+            return;
+        }
+        let location = if from == self.current_workspace {
+            let location = self.space.element_location(window).unwrap_or_default();
+            self.space.unmap_elem(window);
+            window.set_activated(false);
+            window.toplevel().send_pending_configure();
+            location
+        } else {
+            self.workspaces[from].hidden_location(window)
+        };
+        self.workspaces[from].remove(window);
+
+        let workspace = &mut self.workspaces[index];
+        workspace.insert(window.clone(), None);
+        if index == self.current_workspace {
+            self.space.map_element(window.clone(), location, false);
+        } else {
+            workspace.hidden.push((window.clone(), location));
+        }
+
+        self.apply_layout();
+    }
+}